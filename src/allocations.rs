@@ -0,0 +1,183 @@
+//! Allocation tracking: with the `count-allocations` feature, a global allocator counts every
+//! allocation, so a part's allocations can be shown next to its duration.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt::Display,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static REALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting what it is asked for.
+pub struct Counting;
+
+impl Counting {
+    fn grow(by: usize) {
+        BYTES.fetch_add(by, Ordering::Relaxed);
+        let live = LIVE.fetch_add(by, Ordering::Relaxed) + by;
+        PEAK.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn shrink(by: usize) {
+        LIVE.fetch_sub(by, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: Forwarded with the caller's guarantees
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            Counting::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: Forwarded with the caller's guarantees
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            Counting::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: Forwarded with the caller's guarantees
+        unsafe { System.dealloc(ptr, layout) };
+        Counting::shrink(layout.size());
+    }
+
+    /// Only growth counts as allocated bytes, shrinking in place allocates nothing.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: Forwarded with the caller's guarantees
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            REALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            if new_size >= layout.size() {
+                Counting::grow(new_size - layout.size());
+            } else {
+                Counting::shrink(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// What was allocated while something ran, on all threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    pub allocations: usize,
+    pub reallocations: usize,
+    /// Bytes allocated, including the growth of reallocations
+    pub bytes: usize,
+    /// Most bytes that were allocated at the same time on top of the ones from before
+    pub peak: usize,
+}
+
+/// Bytes with a binary unit, e.g. `1.5 KiB`.
+fn bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut unit = 0;
+    let mut tenths = bytes.saturating_mul(10);
+    while tenths >= 10 * 1024 && unit + 1 < UNITS.len() {
+        tenths /= 1024;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{}.{} {}", tenths / 10, tenths % 10, UNITS[unit])
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} allocs ({}), peak {}, {} reallocs",
+            self.allocations,
+            bytes(self.bytes),
+            bytes(self.peak),
+            self.reallocations
+        )
+    }
+}
+
+/// Whether the counting allocator is the global one.
+#[must_use]
+pub fn enabled() -> bool {
+    cfg!(feature = "count-allocations")
+}
+
+/// Runs `f` and returns what it allocated, if the counting allocator is enabled.
+///
+/// Allocations of other threads running at the same time are counted as well.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Option<Stats>) {
+    if !enabled() {
+        return (f(), None);
+    }
+    let live = LIVE.load(Ordering::Relaxed);
+    PEAK.store(live, Ordering::Relaxed);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let reallocations = REALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = BYTES.load(Ordering::Relaxed);
+    let result = f();
+    let stats = Stats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        reallocations: REALLOCATIONS.load(Ordering::Relaxed) - reallocations,
+        bytes: BYTES.load(Ordering::Relaxed) - bytes,
+        peak: PEAK.load(Ordering::Relaxed).saturating_sub(live),
+    };
+    (result, Some(stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let stats = Stats {
+            allocations: 12,
+            reallocations: 3,
+            bytes: 1_572_864,
+            peak: 1_000,
+        };
+        assert_eq!(
+            stats.to_string(),
+            "12 allocs (1.5 MiB), peak 1000 B, 3 reallocs"
+        );
+        assert_eq!(bytes(2_048), "2.0 KiB");
+    }
+
+    #[test]
+    fn test_measure() {
+        let (sum, stats) = measure(|| {
+            let mut numbers: Vec<u64> = Vec::with_capacity(1_000);
+            numbers.extend(0..1_000);
+            numbers.push(1_000);
+            numbers.iter().sum::<u64>()
+        });
+        assert_eq!(sum, 500_500);
+        if enabled() {
+            let stats = stats.unwrap();
+            // Tests on other threads allocate as well
+            assert!(stats.allocations >= 1 && stats.reallocations >= 1);
+            assert!(stats.bytes >= 16_000 && stats.peak > 0);
+        } else {
+            assert_eq!(stats, None);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colour {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Grey,
+}
+
+impl Colour {
+    /// ANSI escape sequence that switches the foreground to this colour.
+    fn ansi(self) -> &'static str {
+        match self {
+            Colour::Red => "\x1b[31m",
+            Colour::Green => "\x1b[32m",
+            Colour::Yellow => "\x1b[33m",
+            Colour::Blue => "\x1b[34m",
+            Colour::Magenta => "\x1b[35m",
+            Colour::Cyan => "\x1b[36m",
+            Colour::Grey => "\x1b[90m",
+        }
+    }
+
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            Colour::Red => [220, 50, 47],
+            Colour::Green => [80, 200, 80],
+            Colour::Yellow => [250, 220, 60],
+            Colour::Blue => [60, 110, 230],
+            Colour::Magenta => [210, 70, 200],
+            Colour::Cyan => [60, 200, 210],
+            Colour::Grey => [110, 110, 110],
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+
+/// One picture of an animation: a title line and a grid of optionally coloured characters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    title: String,
+    lines: Vec<Vec<(char, Option<Colour>)>>,
+}
+
+impl Frame {
+    #[must_use]
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn push_line(&mut self, line: impl IntoIterator<Item = (char, Option<Colour>)>) {
+        self.lines.push(line.into_iter().collect());
+    }
+
+    /// The frame without colours, one line per row below the title.
+    #[must_use]
+    pub fn plain(&self) -> String {
+        let mut text = self.title.clone();
+        text.push('\n');
+        for line in &self.lines {
+            text.extend(line.iter().map(|(c, _)| c));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The frame with ANSI colours, every line clearing what was there before.
+    pub(crate) fn ansi(&self) -> String {
+        let mut text = format!("\x1b[2K{}\n", self.title);
+        for line in &self.lines {
+            text.push_str("\x1b[2K");
+            let mut current = None;
+            for &(c, colour) in line {
+                if colour != current {
+                    text.push_str(colour.map_or(RESET, Colour::ansi));
+                    current = colour;
+                }
+                text.push(c);
+            }
+            if current.is_some() {
+                text.push_str(RESET);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Number of terminal lines the frame takes including its title.
+    pub(crate) fn height(&self) -> usize {
+        self.lines.len() + 1
+    }
+
+    /// The grid as a binary PPM image with `scale` pixels per character.
+    ///
+    /// Coloured characters get their colour, uncoloured ones are white unless they are `.` or a
+    /// space, which are drawn as black background.
+    pub(crate) fn ppm(&self, scale: usize) -> Vec<u8> {
+        let width = self.lines.iter().map(Vec::len).max().unwrap_or(0);
+        let mut image =
+            format!("P6\n{} {}\n255\n", width * scale, self.lines.len() * scale).into_bytes();
+        for line in &self.lines {
+            let pixels: Vec<[u8; 3]> = (0..width)
+                .map(|col| match line.get(col) {
+                    Some((_, Some(colour))) => colour.rgb(),
+                    Some(('.' | ' ', None)) | None => [0, 0, 0],
+                    Some((_, None)) => [255, 255, 255],
+                })
+                .collect();
+            for _ in 0..scale {
+                for pixel in &pixels {
+                    for _ in 0..scale {
+                        image.extend_from_slice(pixel);
+                    }
+                }
+            }
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_rendering() {
+        let mut frame = Frame::new("Step 1");
+        frame.push_line([
+            ('.', None),
+            ('|', Some(Colour::Yellow)),
+            ('|', Some(Colour::Yellow)),
+        ]);
+        frame.push_line([('^', Some(Colour::Red)), ('.', None)]);
+        assert_eq!(frame.plain(), "Step 1\n.||\n^.\n");
+        assert_eq!(
+            frame.ansi(),
+            "\x1b[2KStep 1\n\x1b[2K.\x1b[33m||\x1b[0m\n\x1b[2K\x1b[31m^\x1b[0m.\n"
+        );
+        let image = frame.ppm(1);
+        assert!(image.starts_with(b"P6\n3 2\n255\n"));
+        assert_eq!(image.len(), 11 + 6 * 3);
+        assert_eq!(&image[11..14], &[0, 0, 0]);
+        assert_eq!(&image[14..17], &Colour::Yellow.rgb());
+    }
+}
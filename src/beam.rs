@@ -0,0 +1,499 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+    pub row: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+        }
+    }
+
+    /// The two directions perpendicular to this one.
+    fn sideways(self) -> [Direction; 2] {
+        match self {
+            Direction::Up | Direction::Down => [Direction::Left, Direction::Right],
+            Direction::Left | Direction::Right => [Direction::Up, Direction::Down],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirror {
+    /// `/`
+    Slash,
+    /// `\`
+    Backslash,
+}
+
+impl Mirror {
+    fn reflect(self, direction: Direction) -> Direction {
+        match (self, direction) {
+            (Mirror::Slash, Direction::Right) | (Mirror::Backslash, Direction::Left) => {
+                Direction::Up
+            }
+            (Mirror::Slash, Direction::Left) | (Mirror::Backslash, Direction::Right) => {
+                Direction::Down
+            }
+            (Mirror::Slash, Direction::Down) | (Mirror::Backslash, Direction::Up) => {
+                Direction::Left
+            }
+            (Mirror::Slash, Direction::Up) | (Mirror::Backslash, Direction::Down) => {
+                Direction::Right
+            }
+        }
+    }
+}
+
+/// What happens to a beam that moves onto a tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behaviour {
+    /// The beam moves on unchanged.
+    Pass,
+    /// The beam is replaced by two beams on the cells beside the tile (perpendicular to its
+    /// direction), which keep the direction. The tiles the new beams land on only matter if they
+    /// block them.
+    Split,
+    /// The beam is reflected.
+    Mirror(Mirror),
+    /// The beam enters the tile and ends there.
+    Absorb,
+    /// The beam can't enter the tile and ends in front of it.
+    Block,
+}
+
+/// What happens to a beam that leaves the grid on one side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    /// The beam ends there.
+    Exit,
+    /// The simulation fails, as the grid was expected to contain all beams on this side.
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edges {
+    pub top: Edge,
+    pub bottom: Edge,
+    pub left: Edge,
+    pub right: Edge,
+}
+
+impl Edges {
+    pub const EXIT: Edges = Edges {
+        top: Edge::Exit,
+        bottom: Edge::Exit,
+        left: Edge::Exit,
+        right: Edge::Exit,
+    };
+
+    /// The edge a beam moving in `direction` leaves through.
+    fn towards(self, direction: Direction) -> Edge {
+        match direction {
+            Direction::Up => self.top,
+            Direction::Down => self.bottom,
+            Direction::Left => self.left,
+            Direction::Right => self.right,
+        }
+    }
+}
+
+/// Which tile does what and where the beam starts.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    behaviours: HashMap<char, Behaviour>,
+    start: char,
+    direction: Direction,
+    edges: Edges,
+}
+
+impl Default for Rules {
+    /// Rules of day 7 plus mirrors (`/`, `\`), absorbers (`X`) and walls (`#`). The beam starts at `S`
+    /// moving down and may leave the grid anywhere.
+    fn default() -> Self {
+        Self {
+            behaviours: HashMap::from([
+                ('.', Behaviour::Pass),
+                ('S', Behaviour::Pass),
+                ('^', Behaviour::Split),
+                ('/', Behaviour::Mirror(Mirror::Slash)),
+                ('\\', Behaviour::Mirror(Mirror::Backslash)),
+                ('X', Behaviour::Absorb),
+                ('#', Behaviour::Block),
+            ]),
+            start: 'S',
+            direction: Direction::Down,
+            edges: Edges::EXIT,
+        }
+    }
+}
+
+impl Rules {
+    #[must_use]
+    pub fn with_tile(mut self, tile: char, behaviour: Behaviour) -> Self {
+        self.behaviours.insert(tile, behaviour);
+        self
+    }
+
+    /// The beam starts on the `tile`, which has to occur exactly once, moving in `direction`.
+    #[must_use]
+    pub fn with_start(mut self, tile: char, direction: Direction) -> Self {
+        self.behaviours.entry(tile).or_insert(Behaviour::Pass);
+        self.start = tile;
+        self.direction = direction;
+        self
+    }
+
+    #[must_use]
+    pub fn with_edges(mut self, edges: Edges) -> Self {
+        self.edges = edges;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BeamError {
+    UnknownTile {
+        tile: char,
+        position: Position,
+    },
+    MissingStart,
+    MultipleStarts,
+    /// A beam left the grid on a side whose edge is [`Edge::Fail`].
+    LeftGrid {
+        position: Position,
+        direction: Direction,
+    },
+    /// Beams run in a loop, so there are infinitely many timelines.
+    Loop,
+    Overflow,
+}
+
+impl Display for BeamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BeamError::UnknownTile { tile, position } => write!(
+                f,
+                "Unknown tile {tile:?} at row {}, column {}",
+                position.row, position.col
+            ),
+            BeamError::MissingStart => write!(f, "No start tile"),
+            BeamError::MultipleStarts => write!(f, "More than one start tile"),
+            BeamError::LeftGrid {
+                position,
+                direction,
+            } => write!(
+                f,
+                "Beam left the grid moving {direction:?} from row {}, column {}",
+                position.row, position.col
+            ),
+            BeamError::Loop => write!(f, "Beams run in a loop"),
+            BeamError::Overflow => write!(f, "Number of timelines doesn't fit into 128 bits"),
+        }
+    }
+}
+
+impl std::error::Error for BeamError {}
+
+/// Outcome of following all beams until they ended.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BeamReport {
+    /// Number of distinct splitters hit by at least one beam.
+    pub splits: usize,
+    /// Number of timelines, i.e. the number of beams if every split picked one way only.
+    pub timelines: u128,
+    /// Timelines in which the beam left the grid.
+    pub exited: u128,
+    /// Timelines in which the beam ended in an absorber.
+    pub absorbed: u128,
+    /// Timelines in which the beam ended in front of a wall.
+    pub blocked: u128,
+}
+
+fn add(total: &mut u128, count: u128) -> Result<(), BeamError> {
+    *total = total.checked_add(count).ok_or(BeamError::Overflow)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Ending {
+    Exited,
+    Absorbed,
+    Blocked,
+}
+
+impl BeamReport {
+    fn end(&mut self, ending: Ending, count: u128) -> Result<(), BeamError> {
+        add(&mut self.timelines, count)?;
+        match ending {
+            Ending::Exited => add(&mut self.exited, count),
+            Ending::Absorbed => add(&mut self.absorbed, count),
+            Ending::Blocked => add(&mut self.blocked, count),
+        }
+    }
+}
+
+/// Beams currently in the grid with the number of timelines each one stands for.
+pub type Beams = BTreeMap<(Position, Direction), u128>;
+
+/// A grid of tiles that beams travel through, one cell per step.
+#[derive(Debug, Clone)]
+pub struct BeamGrid {
+    tiles: Vec<Vec<char>>,
+    behaviours: Vec<Vec<Behaviour>>,
+    start: Position,
+    direction: Direction,
+    edges: Edges,
+}
+
+impl BeamGrid {
+    /// Reads one row of tiles per line, ignoring surrounding whitespace.
+    ///
+    /// # Errors
+    /// If a tile has no behaviour in `rules` or there isn't exactly one start tile.
+    pub fn parse(input: &str, rules: &Rules) -> Result<Self, BeamError> {
+        let tiles: Vec<Vec<char>> = input
+            .lines()
+            .map(|line| line.trim_end().chars().collect())
+            .collect();
+        let mut start = None;
+        let mut behaviours = Vec::with_capacity(tiles.len());
+        for (row, line) in tiles.iter().enumerate() {
+            let mut row_behaviours = Vec::with_capacity(line.len());
+            for (col, &tile) in line.iter().enumerate() {
+                let position = Position { row, col };
+                if tile == rules.start && start.replace(position).is_some() {
+                    return Err(BeamError::MultipleStarts);
+                }
+                row_behaviours.push(
+                    *rules
+                        .behaviours
+                        .get(&tile)
+                        .ok_or(BeamError::UnknownTile { tile, position })?,
+                );
+            }
+            behaviours.push(row_behaviours);
+        }
+        Ok(Self {
+            tiles,
+            behaviours,
+            start: start.ok_or(BeamError::MissingStart)?,
+            direction: rules.direction,
+            edges: rules.edges,
+        })
+    }
+
+    fn cell_count(&self) -> usize {
+        self.tiles.iter().map(Vec::len).sum()
+    }
+
+    /// The cell next to `position` in `direction`, if it is inside the grid.
+    fn neighbour(&self, position: Position, direction: Direction) -> Option<Position> {
+        let (d_row, d_col) = direction.offset();
+        let row = position.row.checked_add_signed(d_row)?;
+        let col = position.col.checked_add_signed(d_col)?;
+        (col < self.tiles.get(row)?.len()).then_some(Position { row, col })
+    }
+
+    fn leave(
+        &self,
+        position: Position,
+        direction: Direction,
+        count: u128,
+        report: &mut BeamReport,
+    ) -> Result<(), BeamError> {
+        match self.edges.towards(direction) {
+            Edge::Exit => report.end(Ending::Exited, count),
+            Edge::Fail => Err(BeamError::LeftGrid {
+                position,
+                direction,
+            }),
+        }
+    }
+
+    /// Moves all `count` beams at `position` one cell on.
+    fn step(
+        &self,
+        (position, direction): (Position, Direction),
+        count: u128,
+        next: &mut Beams,
+        report: &mut BeamReport,
+        splitters: &mut HashSet<Position>,
+    ) -> Result<(), BeamError> {
+        let Some(target) = self.neighbour(position, direction) else {
+            return self.leave(position, direction, count, report);
+        };
+        match self.behaviours[target.row][target.col] {
+            Behaviour::Pass => add(next.entry((target, direction)).or_default(), count),
+            Behaviour::Mirror(mirror) => add(
+                next.entry((target, mirror.reflect(direction))).or_default(),
+                count,
+            ),
+            Behaviour::Absorb => report.end(Ending::Absorbed, count),
+            Behaviour::Block => report.end(Ending::Blocked, count),
+            Behaviour::Split => {
+                splitters.insert(target);
+                for side in direction.sideways() {
+                    match self.neighbour(target, side) {
+                        None => self.leave(target, side, count, report)?,
+                        Some(beside)
+                            if self.behaviours[beside.row][beside.col] == Behaviour::Block =>
+                        {
+                            report.end(Ending::Blocked, count)?;
+                        }
+                        Some(beside) => add(next.entry((beside, direction)).or_default(), count)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Follows the beam from the start tile until every timeline ended.
+    ///
+    /// Beams on the same cell moving in the same direction are merged, so each step only looks at
+    /// every cell once, however many timelines pass it.
+    ///
+    /// # Errors
+    /// If beams loop, leave the grid on a failing edge or there are too many timelines to count.
+    pub fn simulate(&self) -> Result<BeamReport, BeamError> {
+        self.simulate_with(|_| {})
+    }
+
+    /// Same as [`BeamGrid::simulate`], but shows `observe` the beams in the grid before every step.
+    ///
+    /// # Errors
+    /// If beams loop, leave the grid on a failing edge or there are too many timelines to count.
+    pub fn simulate_with(&self, mut observe: impl FnMut(&Beams)) -> Result<BeamReport, BeamError> {
+        let mut report = BeamReport::default();
+        let mut splitters = HashSet::new();
+        let mut beams = Beams::from([((self.start, self.direction), 1)]);
+        // Without a loop no beam can be in the same state twice, so all beams end before every
+        // state was used once
+        for _ in 0..=4 * self.cell_count() {
+            if beams.is_empty() {
+                report.splits = splitters.len();
+                return Ok(report);
+            }
+            observe(&beams);
+            let mut next = Beams::new();
+            for (beam, count) in beams {
+                self.step(beam, count, &mut next, &mut report, &mut splitters)?;
+            }
+            beams = next;
+        }
+        Err(BeamError::Loop)
+    }
+
+    /// The tiles as they were read, one row per line.
+    #[must_use]
+    pub fn tiles(&self) -> &[Vec<char>] {
+        &self.tiles
+    }
+}
+
+impl Display for BeamGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.tiles {
+            for tile in line {
+                write!(f, "{tile}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulate(input: &str, rules: &Rules) -> Result<BeamReport, BeamError> {
+        BeamGrid::parse(input, rules)?.simulate()
+    }
+
+    #[test]
+    fn test_split_at_edge() {
+        let input = "S..\n...\n^..";
+        let report = simulate(input, &Rules::default()).unwrap();
+        assert_eq!(report.splits, 1);
+        assert_eq!(report.exited, 2);
+        let rules = Rules::default().with_edges(Edges {
+            left: Edge::Fail,
+            ..Edges::EXIT
+        });
+        assert_eq!(
+            simulate(input, &rules),
+            Err(BeamError::LeftGrid {
+                position: Position { row: 2, col: 0 },
+                direction: Direction::Left
+            })
+        );
+    }
+
+    #[test]
+    fn test_absorbers_and_walls() {
+        let report = simulate(".S.\n.^.\nX.#", &Rules::default()).unwrap();
+        assert_eq!(report.absorbed, 1);
+        assert_eq!(report.blocked, 1);
+        assert_eq!(report.timelines, 2);
+        let rules = Rules::default().with_tile('^', Behaviour::Absorb);
+        let report = simulate(".S.\n.^.", &rules).unwrap();
+        assert_eq!((report.splits, report.absorbed), (0, 1));
+    }
+
+    #[test]
+    fn test_mirrors() {
+        let rules = Rules::default().with_start('>', Direction::Right);
+        let report = simulate("\\>.\\\n....\n\\..\\", &rules);
+        assert_eq!(
+            report,
+            Ok(BeamReport {
+                timelines: 1,
+                exited: 1,
+                ..BeamReport::default()
+            })
+        );
+        assert_eq!(simulate("/.\\\n...\n\\>/", &rules), Err(BeamError::Loop));
+    }
+
+    #[test]
+    fn test_indented_rows_keep_their_columns() {
+        let rules = Rules::default().with_tile(' ', Behaviour::Pass);
+        let report = simulate("S. \r\n ^", &rules).unwrap();
+        assert_eq!((report.splits, report.exited), (0, 1));
+        assert_eq!(
+            simulate("S.\n ^", &Rules::default()),
+            Err(BeamError::UnknownTile {
+                tile: ' ',
+                position: Position { row: 1, col: 0 }
+            })
+        );
+    }
+
+    #[test]
+    fn test_timelines_are_merged() {
+        let input = "..S..\n.....\n..^..\n.....\n.^.^.\n.....";
+        let report = simulate(input, &Rules::default()).unwrap();
+        assert_eq!(report.splits, 3);
+        assert_eq!(report.timelines, 4);
+    }
+}
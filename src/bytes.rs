@@ -0,0 +1,441 @@
+//! Parsing on bytes: scanning integers, splitting and slicing without allocating or checking UTF-8.
+//!
+//! Like in [`crate::parse`], functions get the whole `text` and the `fragment` of it they work on,
+//! so errors point at the right bytes of the input.
+
+use std::ops::{Range, RangeInclusive};
+
+use crate::parse::ParseError;
+
+/// Integer types the scanner reads.
+pub trait Integer: Copy {
+    const ZERO: Self;
+    const SIGNED: bool;
+
+    /// `self * 10 + digit` for positive numbers, `self * 10 - digit` for negative ones, if it fits.
+    fn push_digit(self, digit: u8, negative: bool) -> Option<Self>;
+}
+
+macro_rules! integer {
+    ($signed:literal: $($t:ty),*) => {$(
+        impl Integer for $t {
+            const ZERO: Self = 0;
+            const SIGNED: bool = $signed;
+
+            fn push_digit(self, digit: u8, negative: bool) -> Option<Self> {
+                let shifted = self.checked_mul(10)?;
+                if negative {
+                    shifted.checked_sub(<$t>::from(digit))
+                } else {
+                    shifted.checked_add(<$t>::from(digit))
+                }
+            }
+        }
+    )*};
+}
+
+integer!(false: u8, u16, u32, u64, u128, usize);
+integer!(true: i16, i32, i64, i128, isize);
+
+/// Reads the integer at the start of `bytes` and returns it with the number of bytes it took.
+///
+/// Like [`str::parse`], a `+` may come first and a `-` for signed types. `None` if there are no
+/// digits or the number doesn't fit into `T`.
+#[must_use]
+pub fn integer<T: Integer>(bytes: &[u8]) -> Option<(T, usize)> {
+    let (negative, sign) = match bytes.first() {
+        Some(b'-') if T::SIGNED => (true, 1),
+        Some(b'+') => (false, 1),
+        _ => (false, 0),
+    };
+    let mut value = T::ZERO;
+    let mut digits = 0;
+    for &byte in &bytes[sign..] {
+        if !byte.is_ascii_digit() {
+            break;
+        }
+        value = value.push_digit(byte - b'0', negative)?;
+        digits += 1;
+    }
+    if digits == 0 {
+        return None;
+    }
+    Some((value, sign + digits))
+}
+
+/// All integers in `line`, skipping whatever is between them, e.g. `[1, 2, 3]` in `x=1, y=2..3`.
+///
+/// A `-` right before digits makes the number negative for signed types. Numbers that don't fit
+/// into `T` are skipped.
+pub fn integers<T: Integer>(line: &[u8]) -> impl Iterator<Item = T> + '_ {
+    let mut rest = line;
+    std::iter::from_fn(move || {
+        loop {
+            let start = rest
+                .iter()
+                .position(|byte| byte.is_ascii_digit() || (T::SIGNED && *byte == b'-'))?;
+            rest = &rest[start..];
+            let digits_start = usize::from(rest[0] == b'-');
+            let len = digits_start
+                + rest[digits_start..]
+                    .iter()
+                    .take_while(|byte| byte.is_ascii_digit())
+                    .count();
+            let value = integer(&rest[..len]);
+            rest = &rest[len.max(1)..];
+            if let Some((value, _)) = value {
+                return Some(value);
+            }
+        }
+    })
+}
+
+/// Byte offset of `fragment` in `text`.
+///
+/// # Panics
+/// If `fragment` isn't a slice of `text`.
+fn offset_of(text: &[u8], fragment: &[u8]) -> usize {
+    let offset = fragment
+        .as_ptr()
+        .addr()
+        .checked_sub(text.as_ptr().addr())
+        .filter(|offset| offset + fragment.len() <= text.len());
+    offset.expect("Fragment is not part of the text")
+}
+
+/// Error about `fragment`, which has to be a slice of `text`.
+#[must_use]
+pub fn error_at(text: &[u8], fragment: &[u8], message: impl Into<String>) -> ParseError {
+    ParseError::new(offset_of(text, fragment), fragment.len(), message)
+}
+
+/// The first character of `fragment`, e.g. to point an error at, empty if there is none. A byte
+/// that isn't valid UTF-8 counts as a character of its own.
+#[must_use]
+pub fn first_char(fragment: &[u8]) -> &[u8] {
+    let len = fragment.utf8_chunks().next().map_or(0, |chunk| {
+        chunk.valid().chars().next().map_or(1, char::len_utf8)
+    });
+    &fragment[..len]
+}
+
+/// Parses `fragment` of `text` as a number, ignoring surrounding whitespace.
+///
+/// # Errors
+/// If `fragment` isn't a valid `T`.
+pub fn number<T: Integer>(text: &[u8], fragment: &[u8]) -> Result<T, ParseError> {
+    let trimmed = fragment.trim_ascii();
+    match integer(trimmed) {
+        Some((value, len)) if len == trimmed.len() => Ok(value),
+        _ => Err(error_at(
+            text,
+            trimmed,
+            format!(
+                "Expected a number, found {:?}",
+                String::from_utf8_lossy(trimmed)
+            ),
+        )),
+    }
+}
+
+/// The parts of `bytes` between `delimiter`s.
+pub fn split(bytes: &[u8], delimiter: u8) -> impl Iterator<Item = &[u8]> {
+    bytes.split(move |&byte| byte == delimiter)
+}
+
+/// Splits `fragment` of `text` at the first `delimiter`.
+///
+/// # Errors
+/// If `fragment` doesn't contain `delimiter`.
+pub fn split_once<'a>(
+    text: &[u8],
+    fragment: &'a [u8],
+    delimiter: u8,
+) -> Result<(&'a [u8], &'a [u8]), ParseError> {
+    let at = fragment
+        .iter()
+        .position(|&byte| byte == delimiter)
+        .ok_or_else(|| {
+            error_at(
+                text,
+                fragment,
+                format!("Expected {:?}", char::from(delimiter).to_string()),
+            )
+        })?;
+    Ok((&fragment[..at], &fragment[at + 1..]))
+}
+
+/// The lines of `text` that aren't blank, without the line break.
+pub fn lines(text: &[u8]) -> impl Iterator<Item = &[u8]> {
+    split(text, b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.trim_ascii().is_empty())
+}
+
+/// Parses every line of `text` that isn't blank with `parse`.
+///
+/// # Errors
+/// The error of the first line `parse` fails on.
+pub fn parse_lines<T>(
+    text: &[u8],
+    parse: impl FnMut(&[u8]) -> Result<T, ParseError>,
+) -> Result<Vec<T>, ParseError> {
+    lines(text).map(parse).collect()
+}
+
+/// Parses a range like `11-22` in `fragment` of `text`.
+///
+/// # Errors
+/// If there are no two numbers separated by `-` or the range ends before it starts.
+pub fn range(text: &[u8], fragment: &[u8]) -> Result<RangeInclusive<usize>, ParseError> {
+    let trimmed = fragment.trim_ascii();
+    let (start, end) = split_once(text, trimmed, b'-')?;
+    let (start, end) = (number(text, start)?, number(text, end)?);
+    if end < start {
+        return Err(error_at(text, trimmed, "Range ends before it starts"));
+    }
+    Ok(start..=end)
+}
+
+/// The bytes of `line` in `columns`, cut short where the line ends, e.g. for right-aligned tables
+/// whose lines lack trailing spaces.
+#[must_use]
+pub fn column(line: &[u8], columns: Range<usize>) -> &[u8] {
+    let end = columns.end.min(line.len());
+    &line[columns.start.min(end)..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer() {
+        assert_eq!(integer::<u8>(b"255,"), Some((255, 3)));
+        assert_eq!(integer::<u8>(b"256"), None);
+        assert_eq!(integer::<i16>(b"-32768"), Some((-32768, 6)));
+        assert_eq!(integer::<i16>(b"-32769"), None);
+        assert_eq!(integer::<isize>(b"+7x"), Some((7, 2)));
+        assert_eq!(integer::<usize>(b"-7"), None);
+        assert_eq!(integer::<usize>(b"x"), None);
+        assert_eq!(integer::<usize>(b"-"), None);
+    }
+
+    #[test]
+    fn test_integers() {
+        let line = b"p=-3,14 v=2..-1 big=99999999999999999999 end";
+        assert_eq!(
+            integers::<i64>(line).collect::<Vec<_>>(),
+            vec![-3, 14, 2, -1]
+        );
+        assert_eq!(integers::<u8>(line).collect::<Vec<_>>(), vec![3, 14, 2, 1]);
+        assert_eq!(
+            integers::<i32>(b"--5 - 6-").collect::<Vec<_>>(),
+            vec![-5, 6]
+        );
+    }
+
+    #[test]
+    fn test_errors_match_str_parsers() {
+        let text = b"1-2\n11-x1\n";
+        let line = lines(text).nth(1).unwrap();
+        let error = range(text, line).unwrap_err();
+        let str_error = crate::parse::range("11-x1", "11-x1")
+            .unwrap_err()
+            .within("1-2\n11-x1\n", &"1-2\n11-x1\n"[4..9]);
+        assert_eq!(error, str_error);
+        assert_eq!(
+            split_once(b"a b", b"a b", b':'),
+            Err(ParseError::new(0, 3, "Expected \":\""))
+        );
+        assert_eq!(number::<u8>(text, &text[..1]), Ok(1));
+        assert!(range(b"5-3", b"5-3").is_err());
+    }
+
+    #[test]
+    fn test_lines_and_columns() {
+        let text = b" 12 3\r\n\n  4\n";
+        assert_eq!(
+            lines(text).collect::<Vec<_>>(),
+            vec![&b" 12 3"[..], &b"  4"[..]]
+        );
+        assert_eq!(column(b"  4", 0..3), b"  4");
+        assert_eq!(column(b"  4", 2..5), b"4");
+        assert_eq!(column(b"  4", 4..5), b"");
+        assert_eq!(split(b"1,,2", b',').collect::<Vec<_>>().len(), 3);
+        assert_eq!(first_char("äx".as_bytes()), "ä".as_bytes());
+        assert_eq!(first_char(b"\xFFx"), b"\xFF");
+        assert_eq!(first_char(b""), b"");
+    }
+
+    /// The `str` parsers the days used before they moved to bytes, kept to benchmark against.
+    mod str_parsers {
+        use std::ops::RangeInclusive;
+
+        use crate::parse::{self, ParseError};
+
+        fn lines(text: &str) -> impl Iterator<Item = &str> {
+            text.lines().filter(|line| !line.trim().is_empty())
+        }
+
+        pub fn day01(input: &str) -> Result<Vec<(bool, isize)>, ParseError> {
+            lines(input)
+                .map(|line| {
+                    let dir_len = line.chars().next().map_or(0, char::len_utf8);
+                    let (dir, steps) = line.split_at(dir_len);
+                    let steps: isize = parse::number(line, steps)?;
+                    match dir {
+                        "L" => Ok((true, steps)),
+                        "R" => Ok((false, steps)),
+                        _ => Err(ParseError::at(line, dir, "Expected L or R")),
+                    }
+                })
+                .collect()
+        }
+
+        pub fn day05(input: &str) -> Result<(Vec<RangeInclusive<usize>>, Vec<usize>), ParseError> {
+            let (ranges, items) = input
+                .split_once("\n\n")
+                .ok_or_else(|| ParseError::at_end(input, "Expected an empty line"))?;
+            let fresh = lines(ranges)
+                .map(|line| parse::range(input, line))
+                .collect::<Result<_, _>>()?;
+            let items = lines(items)
+                .map(|line| parse::number(input, line))
+                .collect::<Result<_, _>>()?;
+            Ok((fresh, items))
+        }
+
+        pub fn day08(input: &str) -> Result<Vec<[usize; 3]>, ParseError> {
+            lines(input)
+                .map(|line| {
+                    let (x, rest) = parse::split_once(line, line, ",")?;
+                    let (y, z) = parse::split_once(line, rest, ",")?;
+                    Ok([
+                        parse::number(line, x)?,
+                        parse::number(line, y)?,
+                        parse::number(line, z)?,
+                    ])
+                })
+                .collect()
+        }
+
+        pub fn day09(input: &str) -> Result<Vec<(usize, usize)>, ParseError> {
+            lines(input)
+                .map(|line| {
+                    let (x, y) = parse::split_once(input, line, ",")?;
+                    Ok((parse::number(input, x)?, parse::number(input, y)?))
+                })
+                .collect()
+        }
+
+        type Machine = (Vec<bool>, Vec<Vec<usize>>, Vec<usize>);
+
+        fn delimited<'a>(
+            s: &str,
+            fragment: &'a str,
+            open: char,
+            close: char,
+        ) -> Result<&'a str, ParseError> {
+            fragment
+                .strip_prefix(open)
+                .and_then(|inner| inner.strip_suffix(close))
+                .ok_or_else(|| ParseError::at(s, fragment, format!("Expected {open}...{close}")))
+        }
+
+        fn numbers(s: &str, list: &str) -> Result<Vec<usize>, ParseError> {
+            list.split(',')
+                .map(|number| parse::number(s, number))
+                .collect()
+        }
+
+        fn machine(s: &str) -> Result<Machine, ParseError> {
+            let mut parts = s.split_ascii_whitespace();
+            let lights = parts
+                .next()
+                .ok_or_else(|| ParseError::at_end(s, "Expected lights"))?;
+            let lights = delimited(s, lights, '[', ']')?
+                .char_indices()
+                .map(|(i, c)| match c {
+                    '#' => Ok(true),
+                    '.' => Ok(false),
+                    _ => Err(ParseError::new(
+                        parse::offset_of(s, lights) + 1 + i,
+                        c.len_utf8(),
+                        "Expected light # or .",
+                    )),
+                })
+                .collect::<Result<_, _>>()?;
+            let mut buttons = Vec::new();
+            let mut joltages = None;
+            for part in parts {
+                if part.starts_with('{') {
+                    joltages = Some(numbers(s, delimited(s, part, '{', '}')?)?);
+                } else {
+                    buttons.push(numbers(s, delimited(s, part, '(', ')')?)?);
+                }
+            }
+            let joltages = joltages.ok_or_else(|| ParseError::at_end(s, "Expected joltages"))?;
+            Ok((lights, buttons, joltages))
+        }
+
+        pub fn day10(input: &str) -> Result<Vec<Machine>, ParseError> {
+            lines(input).map(machine).collect()
+        }
+    }
+
+    /// Fastest of 7 runs of `parse` on `input` in milliseconds.
+    fn fastest<T>(parse: impl Fn(&str) -> T, input: &str) -> f64 {
+        (0..7)
+            .map(|_| {
+                let start = std::time::Instant::now();
+                std::hint::black_box(parse(std::hint::black_box(input)));
+                start.elapsed().as_secs_f64() * 1e3
+            })
+            .fold(f64::MAX, f64::min)
+    }
+
+    #[test]
+    #[ignore = "Benchmark, run with `cargo test --release bench_against_str -- --ignored --nocapture`"]
+    fn bench_against_str_parsers() {
+        use crate::days::{day01, day05, day08, day09, day10};
+
+        println!("Parsing generated inputs, fastest of 7 runs, str vs bytes:");
+        for (day, size) in [
+            (1, 200_000),
+            (5, 100_000),
+            (8, 100_000),
+            (9, 100_000),
+            (10, 20_000),
+        ] {
+            let input = crate::generate::generate(day, size, 0).unwrap();
+            let (str_time, bytes_time) = match day {
+                1 => (
+                    fastest(|input| str_parsers::day01(input).unwrap(), &input),
+                    fastest(day01::parse_rotations, &input),
+                ),
+                5 => (
+                    fastest(|input| str_parsers::day05(input).unwrap(), &input),
+                    fastest(|input| input.parse::<day05::Database>().unwrap(), &input),
+                ),
+                8 => (
+                    fastest(|input| str_parsers::day08(input).unwrap(), &input),
+                    fastest(|input| day08::parse_positions(input).unwrap(), &input),
+                ),
+                9 => (
+                    fastest(|input| str_parsers::day09(input).unwrap(), &input),
+                    fastest(|input| day09::parse_corners(input).unwrap(), &input),
+                ),
+                _ => (
+                    fastest(|input| str_parsers::day10(input).unwrap(), &input),
+                    fastest(|input| day10::parse_machines(input).unwrap(), &input),
+                ),
+            };
+            println!(
+                "day {day:02} {:>8} bytes {str_time:>7.2}ms -> {bytes_time:>7.2}ms {:.1}x",
+                input.len(),
+                str_time / bytes_time
+            );
+        }
+    }
+}
@@ -1,13 +1,13 @@
-use crate::solution::{Solution, SolvedValue};
+use crate::solution::{Context, Solution, SolvedValue};
 
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, _input: &str) -> Option<SolvedValue> {
+    fn part1(&self, _input: &str, _ctx: &Context) -> Option<SolvedValue> {
         None
     }
 
-    fn part2(&self, _input: &str) -> Option<SolvedValue> {
+    fn part2(&self, _input: &str, _ctx: &Context) -> Option<SolvedValue> {
         None
     }
 }
@@ -16,29 +16,33 @@ impl Solution for Day {
 mod tests {
     use super::*;
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 0;
     
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), None);
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), None);
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), None);
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), None);
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), None);
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), None);
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), None);
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), None);
     }
 }
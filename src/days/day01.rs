@@ -1,8 +1,11 @@
-use std::str::FromStr;
+use std::fmt::Display;
 
-use anyhow::anyhow;
-
-use crate::solution::{Solution, SolvedValue};
+use crate::{
+    bytes,
+    generate::Rng,
+    parse::{self, ParseError},
+    solution::{Context, Solution, SolvedValue},
+};
 
 struct Dial {
     position: isize,
@@ -44,7 +47,7 @@ impl Dial {
     }
 }
 
-enum Rotation {
+pub(crate) enum Rotation {
     L(isize),
     R(isize),
 }
@@ -57,32 +60,47 @@ impl Rotation {
     }
 }
 
-impl FromStr for Rotation {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (dir, steps) = s.split_at(1);
-        let steps: isize = steps.parse()?;
-        match dir {
-            "L" => Ok(Rotation::L(steps)),
-            "R" => Ok(Rotation::R(steps)),
-            _ => Err(anyhow!("Invalid rotation direction {dir}")),
+impl Display for Rotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rotation::L(steps) => write!(f, "L{steps}"),
+            Rotation::R(steps) => write!(f, "R{steps}"),
         }
     }
 }
 
-fn parse_rotations(input: &str) -> Vec<Rotation> {
-    input
-        .trim()
-        .lines()
-        .map(|s| s.parse().expect("Failed to parse rotation"))
-        .collect()
+/// Parses a rotation like `L68` in `line` of `text`.
+fn parse_rotation(text: &[u8], line: &[u8]) -> Result<Rotation, ParseError> {
+    let rotation: fn(isize) -> Rotation = match line.first() {
+        Some(b'L') => Rotation::L,
+        Some(b'R') => Rotation::R,
+        _ => {
+            let dir = bytes::first_char(line);
+            return Err(bytes::error_at(
+                text,
+                dir,
+                format!(
+                    "Expected rotation direction L or R, found {:?}",
+                    String::from_utf8_lossy(dir)
+                ),
+            ));
+        }
+    };
+    Ok(rotation(bytes::number(text, &line[1..])?))
+}
+
+pub(crate) fn parse_rotations(input: &str) -> Vec<Rotation> {
+    let text = input.as_bytes();
+    parse::or_report(
+        bytes::parse_lines(text, |line| parse_rotation(text, line)),
+        input,
+    )
 }
 
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
+    fn part1(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
         let rotations = parse_rotations(input);
         let mut dial = Dial::new();
         let mut times_0_reached = 0;
@@ -91,48 +109,135 @@ impl Solution for Day {
             if dial.position == 0 {
                 times_0_reached += 1;
             }
+            ctx.explain(|| {
+                format!(
+                    "{rotation}: dial at {}, 0 reached {times_0_reached} times so far",
+                    dial.position
+                )
+            });
         }
         Some(times_0_reached.into())
     }
 
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
+    fn part2(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
         let rotations = parse_rotations(input);
         let mut dial = Dial::new();
         let mut times_0_passed = 0;
         for rotation in rotations {
-            times_0_passed += dial.rotate(&rotation);
+            let crossed = dial.rotate(&rotation);
+            times_0_passed += crossed;
+            ctx.explain(|| {
+                format!(
+                    "{rotation}: dial at {}, passed 0 {crossed} times",
+                    dial.position
+                )
+            });
         }
         Some(times_0_passed.into())
     }
+
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        let rotations: Vec<String> = (0..size)
+            .map(|_| {
+                let steps = rng.range(1..=999).cast_signed();
+                let rotation = if rng.chance(1, 2) {
+                    Rotation::L(steps)
+                } else {
+                    Rotation::R(steps)
+                };
+                format!("{rotation}\n")
+            })
+            .collect();
+        Some(rotations.concat())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::property::{check, equal};
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 1;
 
+    #[test]
+    fn test_parse_errors() {
+        let text = "L5\näx12\n".as_bytes();
+        let line = bytes::lines(text).nth(1).unwrap();
+        assert_eq!(
+            parse_rotation(text, line).err(),
+            Some(ParseError::new(
+                3,
+                2,
+                "Expected rotation direction L or R, found \"ä\""
+            ))
+        );
+        assert_eq!(
+            parse_rotation(b"Rx", b"Rx").err(),
+            Some(ParseError::new(1, 1, "Expected a number, found \"x\""))
+        );
+    }
+
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(3.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(3.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(1011.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(1011.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(6.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(6.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(5937.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(5937.into()));
+    }
+
+    #[test]
+    fn test_rotate_matches_clicking() {
+        // A rotation from a start position as (start, (left, clicks))
+        check(
+            |rng| {
+                let (start, left) = (rng.range(0..=99), rng.chance(1, 2));
+                // Half of the rotations end on 0, which is easy to miscount
+                let to_zero = if left { start } else { (100 - start) % 100 };
+                let clicks = if rng.chance(1, 2) {
+                    to_zero + 100 * rng.range(0..=9)
+                } else {
+                    rng.range(0..=1_000)
+                };
+                (start, (left, clicks))
+            },
+            |&(start, (left, clicks))| {
+                let amount = isize::try_from(clicks).unwrap();
+                let rotation = if left {
+                    Rotation::L(amount)
+                } else {
+                    Rotation::R(amount)
+                };
+                let mut dial = Dial {
+                    position: isize::try_from(start).unwrap(),
+                };
+                let mut position = dial.position;
+                let mut zeros = 0;
+                for _ in 0..clicks {
+                    position = (position + if left { -1 } else { 1 }).rem_euclid(100);
+                    zeros += usize::from(position == 0);
+                }
+                equal(&(dial.rotate(&rotation), dial.position), &(zeros, position))
+            },
+        );
     }
 }
@@ -1,40 +1,26 @@
 use std::ops::RangeInclusive;
 
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-
-use crate::solution::{Solution, SolvedValue};
+use crate::{
+    digits::{sum_repeated_in_range, sum_repeating_in_range},
+    generate::Rng,
+    parse,
+    solution::{Context, Implementation, Part, Solution, SolvedValue},
+};
 
 fn parse_input_to_ranges(input: &str) -> Vec<RangeInclusive<usize>> {
-    input
+    let ranges = input
         .split(',')
-        .filter_map(|line| {
-            if let Some((start_str, end_str)) = line.split_once('-') {
-                let start = start_str.trim().parse().ok()?;
-                let end = end_str.trim().parse().ok()?;
-                Some(start..=end)
-            } else {
-                None
-            }
-        })
-        .collect()
+        .filter(|range| !range.trim().is_empty())
+        .map(|range| parse::range(input, range))
+        .collect();
+    parse::or_report(ranges, input)
 }
 
-fn get_base10_half_number(number: usize, rounding: u32) -> usize {
-    if number < 10 {
-        1
-    } else {
-        number / 10_usize.pow((number.ilog10() + rounding).div_ceil(2))
-    }
-}
-
-fn find_doubles_in_range(range: &RangeInclusive<usize>) -> Vec<usize> {
-    let half_start = get_base10_half_number(*range.start(), 1);
-    let half_end = get_base10_half_number(*range.end(), 0);
-
-    (half_start..=half_end)
-        .map(|half| half * 10_usize.pow(half.ilog10() + 1) + half)
-        .filter(|n| range.contains(n))
-        .collect()
+/// Whether `number` consists of the same digits repeated exactly twice, like 1212.
+fn is_double(number: usize) -> bool {
+    let digits = number.to_string();
+    let (first, second) = digits.split_at(digits.len() / 2);
+    first == second
 }
 
 fn has_number_repetitions(number: usize) -> bool {
@@ -57,57 +43,161 @@ fn get_range_repitions(range: &RangeInclusive<usize>) -> Vec<usize> {
         .collect()
 }
 
+/// Part 1 checking every id of every range.
+fn brute_force_part1(input: &str) -> SolvedValue {
+    parse_input_to_ranges(input)
+        .into_iter()
+        .flatten()
+        .filter(|&id| is_double(id))
+        .sum::<usize>()
+        .into()
+}
+
+/// Part 2 checking every id of every range.
+fn brute_force_part2(input: &str) -> SolvedValue {
+    SolvedValue::wide(
+        parse_input_to_ranges(input)
+            .iter()
+            .flat_map(get_range_repitions)
+            .map(|id| id as u128)
+            .sum(),
+    )
+}
+
+/// Sum of the invalid ids of all ranges, with `sum` giving the sum of a single range.
+fn sum_invalid_ids(
+    input: &str,
+    ctx: &Context,
+    sum: impl Fn(&RangeInclusive<usize>) -> u128,
+) -> u128 {
+    parse_input_to_ranges(input)
+        .iter()
+        .map(|range| {
+            let range_sum = sum(range);
+            ctx.explain(|| {
+                format!(
+                    "{}-{}: invalid ids sum up to {range_sum}",
+                    range.start(),
+                    range.end()
+                )
+            });
+            range_sum
+        })
+        .sum()
+}
+
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
-        Some(
-            parse_input_to_ranges(input)
-                .iter()
-                .flat_map(find_doubles_in_range)
-                .sum::<usize>()
-                .into(),
-        )
+    fn part1(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        Some(SolvedValue::wide(sum_invalid_ids(input, ctx, |range| {
+            sum_repeated_in_range(range, 2)
+        })))
+    }
+
+    fn part2(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        Some(SolvedValue::wide(sum_invalid_ids(
+            input,
+            ctx,
+            sum_repeating_in_range,
+        )))
     }
 
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
-        Some(
-            parse_input_to_ranges(input)
-                .par_iter()
-                .flat_map(get_range_repitions)
-                .sum::<usize>()
-                .into(),
-        )
+    fn implementations(&self) -> &'static [Implementation] {
+        &[
+            Implementation {
+                name: "brute_force",
+                part: Part::One,
+                solve: |input, _ctx| Some(brute_force_part1(input)),
+            },
+            Implementation {
+                name: "brute_force",
+                part: Part::Two,
+                solve: |input, _ctx| Some(brute_force_part2(input)),
+            },
+        ]
+    }
+
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        let ranges: Vec<String> = (0..size)
+            .map(|_| {
+                let digits = u32::try_from(rng.range(1..=10)).expect("Digit count fits");
+                let start = rng.range(10_usize.pow(digits - 1)..=10_usize.pow(digits) - 1);
+                format!("{start}-{}", start + rng.range(0..=100_000))
+            })
+            .collect();
+        Some(ranges.join(",") + "\n")
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::property::{check, equal};
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 2;
 
+    #[test]
+    fn test_repetitions_match_brute_force() {
+        let input = read_input(DAY, true, 2).unwrap();
+        for range in parse_input_to_ranges(&input) {
+            assert_eq!(
+                crate::digits::count_repeating_in_range(&range),
+                get_range_repitions(&range).len() as u128
+            );
+        }
+    }
+
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(1_227_775_554.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(1_227_775_554.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(9_188_031_749.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(9_188_031_749.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(4_174_379_265.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(4_174_379_265.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(11_323_661_261.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(11_323_661_261.into()));
+    }
+
+    #[test]
+    fn test_repetitions_match_generator() {
+        // Ranges as (start, length)
+        check(
+            |rng| {
+                let digits = u32::try_from(rng.range(1..=12)).unwrap();
+                (rng.range(0..=10_usize.pow(digits)), rng.range(0..=2_000))
+            },
+            |&(start, length)| {
+                let range = start..=start + length;
+                let repetitions = get_range_repitions(&range);
+                equal(
+                    &(
+                        repetitions.len() as u128,
+                        repetitions.iter().map(|&n| n as u128).sum(),
+                    ),
+                    &(
+                        crate::digits::count_repeating_in_range(&range),
+                        crate::digits::sum_repeating_in_range(&range),
+                    ),
+                )
+            },
+        );
     }
 }
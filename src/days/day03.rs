@@ -1,115 +1,281 @@
 use std::str::FromStr;
 
-use crate::solution::{Solution, SolvedValue};
+use crate::{
+    generate::Rng,
+    parse::{self, ParseError},
+    solution::{Context, Parameter, Solution, SolvedValue, Violation, parses_as},
+};
 
 type Battery = u8;
 
-fn max_battery_with_index(iter: &[Battery]) -> (usize, &Battery) {
-    let mut max_i = 0;
-    let mut max = &0;
-    for (i, n) in iter.iter().enumerate() {
-        if n > max {
-            max_i = i;
-            max = n;
-        }
-    }
-    (max_i, max)
+/// The batteries chosen to be turned on and the joltage they produce together.
+#[derive(Debug, PartialEq, Eq)]
+struct Power {
+    indices: Vec<usize>,
+    value: u128,
 }
 
 #[derive(Debug)]
 struct Bank(Vec<Battery>);
 
 impl Bank {
-    fn max_power_2(&self) -> usize {
-        let (max_i, max) = max_battery_with_index(&self.0[0..(self.0.len() - 1)]);
-        *max as usize * 10 + *self.0.iter().skip(max_i + 1).max().unwrap() as usize
-    }
-
-    fn max_power_12(&self) -> usize {
-        let mut start = 0;
-        let mut res = 0;
-        for i in 0..12 {
-            let end = self.0.len() - 12 + i;
-            let (max_i, max) = max_battery_with_index(&self.0[start..=end]);
-            start += max_i + 1;
-            res = res * 10 + *max as usize;
+    /// Picks `k` batteries (keeping their order) that form the largest possible number.
+    ///
+    /// Uses a monotonic stack: a battery replaces smaller ones before it as long as enough batteries
+    /// are left to still pick `k` in total. Returns `None` if the bank has fewer than `k` batteries
+    /// or the result doesn't fit into a `u128`.
+    fn max_power(&self, k: usize) -> Option<Power> {
+        let mut droppable = self.0.len().checked_sub(k)?;
+        let mut stack: Vec<usize> = Vec::with_capacity(self.0.len());
+        for (i, battery) in self.0.iter().enumerate() {
+            while droppable > 0 && stack.last().is_some_and(|&top| self.0[top] < *battery) {
+                stack.pop();
+                droppable -= 1;
+            }
+            stack.push(i);
         }
-        res
+        stack.truncate(k);
+        let value = stack.iter().try_fold(0u128, |acc, &i| {
+            acc.checked_mul(10)?.checked_add(u128::from(self.0[i]))
+        })?;
+        Some(Power {
+            indices: stack,
+            value,
+        })
     }
 }
 
 impl FromStr for Bank {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Bank(
-            s.chars()
-                .filter_map(|c| {
-                    c.to_digit(10)
-                        .map(|x| u8::try_from(x).expect("Unable to convert number"))
-                })
-                .collect(),
-        ))
+        s.trim_end()
+            .char_indices()
+            .map(|(i, c)| {
+                c.to_digit(10)
+                    .and_then(|x| Battery::try_from(x).ok())
+                    .ok_or_else(|| {
+                        ParseError::new(
+                            i,
+                            c.len_utf8(),
+                            format!("Expected a joltage digit, found {c:?}"),
+                        )
+                    })
+            })
+            .collect::<Result<_, _>>()
+            .map(Bank)
     }
 }
 
 fn parse_input(input: &str) -> Vec<Bank> {
-    input.lines().filter_map(|line| line.parse().ok()).collect()
+    parse::or_report(parse::lines(input), input)
+}
+
+/// Sum of the largest powers of all banks with `k` batteries turned on, giving up on the input if
+/// a bank is too short or a power doesn't fit into 128 bits.
+fn total_power(banks: &[Bank], k: usize, ctx: &Context) -> u128 {
+    let total = banks
+        .iter()
+        .enumerate()
+        .try_fold(0u128, |total, (i, bank)| {
+            let power = bank.max_power(k).unwrap_or_else(|| {
+                if bank.0.len() < k {
+                    parse::reject(format!(
+                        "Bank {} has {} batteries, but {k} are turned on",
+                        i + 1,
+                        bank.0.len()
+                    ))
+                }
+                parse::reject(format!(
+                    "The power of bank {} doesn't fit into 128 bits",
+                    i + 1
+                ))
+            });
+            ctx.explain(|| {
+                format!(
+                    "Bank {}: batteries {:?} give {}",
+                    i + 1,
+                    power.indices,
+                    power.value
+                )
+            });
+            total.checked_add(power.value).ok_or(i)
+        });
+    total.unwrap_or_else(|i| {
+        parse::reject(format!(
+            "The total power doesn't fit into 128 bits once bank {} is added",
+            i + 1
+        ))
+    })
 }
 
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
-        Some(
-            parse_input(input)
-                .iter()
-                .map(Bank::max_power_2)
-                .sum::<usize>()
-                .into(),
-        )
-    }
-
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
-        Some(
-            parse_input(input)
-                .iter()
-                // .inspect(|x| print!("{x:?}"))
-                .map(Bank::max_power_12)
-                // .inspect(|x| println!("\t{x:?}"))
-                .sum::<usize>()
-                .into(),
-        )
+    fn part1(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let k = ctx.param("part1_batteries");
+        Some(SolvedValue::wide(total_power(&parse_input(input), k, ctx)))
+    }
+
+    fn part2(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let k = ctx.param("part2_batteries");
+        Some(SolvedValue::wide(total_power(&parse_input(input), k, ctx)))
+    }
+
+    fn validate(&self, input: &str, ctx: &Context) -> Vec<Violation> {
+        let k: usize = ctx
+            .param::<usize>("part1_batteries")
+            .max(ctx.param("part2_batteries"));
+        let mut violations = Vec::new();
+        for (i, line) in input.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match line.parse::<Bank>() {
+                Ok(bank) if bank.0.len() < k => violations.push(Violation::new(
+                    "Banks have enough batteries to turn on",
+                    format!(
+                        "Bank in line {} has {} batteries, but {k} are turned on",
+                        i + 1,
+                        bank.0.len()
+                    ),
+                )),
+                Ok(_) => {}
+                Err(e) => return vec![Violation::malformed(input, &e.within(input, line))],
+            }
+        }
+        violations
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                name: "part1_batteries",
+                default: "2",
+                description: "Number of batteries to turn on per bank in part 1",
+                check: parses_as::<usize>,
+            },
+            Parameter {
+                name: "part2_batteries",
+                default: "12",
+                description: "Number of batteries to turn on per bank in part 2",
+                check: parses_as::<usize>,
+            },
+        ]
+    }
+
+    /// Banks of 100 batteries like the real input.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        Some((0..size).map(|_| rng.digits(100) + "\n").collect())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::property::{check, equal};
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 3;
 
+    #[test]
+    fn test_max_power_indices() {
+        let bank: Bank = "818181911112111".parse().unwrap();
+        assert_eq!(
+            bank.max_power(3),
+            Some(Power {
+                indices: vec![6, 11, 12],
+                value: 921
+            })
+        );
+        assert_eq!(bank.max_power(16), None);
+        assert_eq!(bank.max_power(0).map(|power| power.value), Some(0));
+    }
+
+    #[test]
+    fn test_validate() {
+        let ctx = Context::new(&Day, false);
+        assert_eq!(
+            Day.validate("987654321111111\n\n811111111111119\n", &ctx),
+            vec![]
+        );
+        assert_eq!(
+            Day.validate("987654321111111\n12345\n", &ctx),
+            vec![Violation::new(
+                "Banks have enough batteries to turn on",
+                "Bank in line 2 has 5 batteries, but 12 are turned on"
+            )]
+        );
+        assert!(
+            Day.validate("987654321111111\n12x45\n", &ctx)[0]
+                .detail
+                .contains("line 2, column 3")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Bank 2 has 3 batteries, but 4 are turned on")]
+    fn test_short_bank_is_rejected() {
+        total_power(&parse_input("12345\n123\n"), 4, &Context::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "The power of bank 1 doesn't fit into 128 bits")]
+    fn test_overflow_is_rejected() {
+        total_power(&parse_input(&"9".repeat(40)), 39, &Context::default());
+    }
+
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(357.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(357.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(17109.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(17109.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(3_121_910_778_619.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(3_121_910_778_619.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(169_347_417_057_382.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(169_347_417_057_382.into()));
+    }
+
+    #[test]
+    fn test_max_power_matches_brute_force() {
+        fn brute_force(bank: &[Battery], k: usize) -> Option<u128> {
+            (0_u32..1 << bank.len())
+                .filter(|subset| subset.count_ones() as usize == k)
+                .map(|subset| {
+                    (0..bank.len())
+                        .filter(|i| subset & 1 << i != 0)
+                        .fold(0, |acc, i| acc * 10 + u128::from(bank[i]))
+                })
+                .max()
+        }
+        // Banks of up to 14 batteries and how many of them to turn on
+        check(
+            |rng| {
+                let bank = (0..rng.range(1..=14))
+                    .map(|_| u8::try_from(rng.range(1..=9)).unwrap())
+                    .collect::<Vec<_>>();
+                (bank, rng.range(1..=12))
+            },
+            |(bank, k)| {
+                let power = Bank(bank.clone()).max_power(*k).map(|power| power.value);
+                equal(&power, &brute_force(bank, *k))
+            },
+        );
     }
 }
@@ -1,121 +1,232 @@
-use std::str::FromStr;
+use std::{collections::VecDeque, str::FromStr};
 
-use crate::solution::{Solution, SolvedValue};
+use crate::{
+    animation::{Colour, Frame},
+    generate::Rng,
+    observe::{Event, Level},
+    parse::{self, ParseError},
+    solution::{Context, Solution, SolvedValue, Violation},
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 struct Position {
     row: usize,
     col: usize,
 }
 
+const NEIGHBOUR_OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+];
+
+/// A roll can be reached by a forklift if fewer than this many rolls surround it.
+const MAX_REACHABLE_NEIGHBOURS: usize = 4;
+
 struct Map(Vec<Vec<bool>>);
 
 impl Map {
-    fn number_of_neighbour_rolls(&self, pos: &Position) -> usize {
-        // Bitmask as follows:
-        // 012
-        // 7X3
-        // 654
-        let mut bitmask: u8 = 0xff;
-        if pos.row == 0 {
-            bitmask &= 0b0001_1111;
-        }
-        if pos.col == self.0[0].len() - 1 {
-            bitmask &= 0b1100_0111;
-        }
-        if pos.row == self.0[0].len() - 1 {
-            bitmask &= 0b1111_0001;
-        }
-        if pos.col == 0 {
-            bitmask &= 0b0111_1100;
-        }
-        [
-            (-1, -1),
-            (-1, 0),
-            (-1, 1),
-            (0, 1),
-            (1, 1),
-            (1, 0),
-            (1, -1),
-            (0, -1),
-        ]
-        .iter()
-        .enumerate()
-        .filter(|(index, _)| bitmask & (1 << (7 - index)) != 0)
-        .filter(|(_, item)| {
-            // println!("Position: {pos:?}, Item: {item:?}");
-            // Safety:
-            // Can only be valid here
-            self.0[pos
-                .row
-                .checked_add_signed(item.0)
-                .expect("Could not add to row")][pos
-                .col
-                .checked_add_signed(item.1)
-                .expect("Could not add to col")]
+    fn is_roll(&self, pos: Position) -> bool {
+        self.0
+            .get(pos.row)
+            .and_then(|row| row.get(pos.col))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    fn neighbours(pos: Position) -> impl Iterator<Item = Position> {
+        NEIGHBOUR_OFFSETS.iter().filter_map(move |&(d_row, d_col)| {
+            Some(Position {
+                row: pos.row.checked_add_signed(d_row)?,
+                col: pos.col.checked_add_signed(d_col)?,
+            })
+        })
+    }
+
+    fn number_of_neighbour_rolls(&self, pos: Position) -> usize {
+        Self::neighbours(pos)
+            .filter(|&neighbour| self.is_roll(neighbour))
+            .count()
+    }
+
+    fn rolls(&self) -> impl Iterator<Item = Position> {
+        self.0.iter().enumerate().flat_map(|(row, line)| {
+            line.iter()
+                .enumerate()
+                .filter(|(_, is_roll)| **is_roll)
+                .map(move |(col, _)| Position { row, col })
         })
-        .count()
     }
 
     fn reachable_roll_count(&self) -> usize {
-        self.reachables().len()
+        self.rolls()
+            .filter(|&pos| self.number_of_neighbour_rolls(pos) < MAX_REACHABLE_NEIGHBOURS)
+            .count()
     }
 
-    fn reachables(&self) -> Vec<Position> {
-        let mut reachables = vec![];
-        for row in 0..self.0.len() {
-            for col in 0..self.0[row].len() {
-                if self.0[row][col] && self.number_of_neighbour_rolls(&Position { row, col }) < 4 {
-                    reachables.push(Position { row, col });
+    /// Computes in which wave each roll gets removed if all reachable rolls are removed at once
+    /// until none are reachable anymore. Rolls that stay have no wave.
+    ///
+    /// Instead of rescanning the map after every wave, the number of neighbouring rolls is kept per
+    /// cell and only the neighbours of a removed roll are updated. A roll is queued for the next wave
+    /// as soon as its count drops below the limit.
+    fn removal_waves(&self) -> Vec<Vec<Option<usize>>> {
+        let mut counts: Vec<Vec<usize>> = self
+            .0
+            .iter()
+            .enumerate()
+            .map(|(row, line)| {
+                (0..line.len())
+                    .map(|col| self.number_of_neighbour_rolls(Position { row, col }))
+                    .collect()
+            })
+            .collect();
+        let mut waves: Vec<Vec<Option<usize>>> =
+            self.0.iter().map(|line| vec![None; line.len()]).collect();
+        let mut queue = VecDeque::new();
+        for pos in self.rolls() {
+            if counts[pos.row][pos.col] < MAX_REACHABLE_NEIGHBOURS {
+                waves[pos.row][pos.col] = Some(1);
+                queue.push_back((pos, 1));
+            }
+        }
+
+        while let Some((pos, wave)) = queue.pop_front() {
+            for neighbour in Self::neighbours(pos) {
+                if self.is_roll(neighbour) && waves[neighbour.row][neighbour.col].is_none() {
+                    let count = &mut counts[neighbour.row][neighbour.col];
+                    *count -= 1;
+                    if *count < MAX_REACHABLE_NEIGHBOURS {
+                        waves[neighbour.row][neighbour.col] = Some(wave + 1);
+                        queue.push_back((neighbour, wave + 1));
+                    }
                 }
             }
         }
-        reachables
+        waves
     }
 
-    fn remove_reachables(&mut self) -> Option<usize> {
-        let reachables = self.reachables();
-        for reachable in &reachables {
-            self.0[reachable.row][reachable.col] = false;
-        }
-        if reachables.is_empty() {
-            None
-        } else {
-            Some(reachables.len())
+    /// Draws the map after `wave`, highlighting the rolls removed in it.
+    fn wave_frame(&self, waves: &[Vec<Option<usize>>], wave: usize) -> Frame {
+        let removed = waves
+            .iter()
+            .flatten()
+            .filter(|w| w.is_some_and(|w| w <= wave))
+            .count();
+        let mut frame = Frame::new(format!("Wave {wave}: {removed} rolls removed"));
+        for (line, waves) in self.0.iter().zip(waves) {
+            frame.push_line(line.iter().zip(waves).map(
+                |(&is_roll, removed_in)| match removed_in {
+                    Some(w) if *w == wave => ('x', Some(Colour::Red)),
+                    Some(w) if *w < wave => ('.', Some(Colour::Grey)),
+                    _ if is_roll => ('@', Some(Colour::Green)),
+                    _ => ('.', None),
+                },
+            ));
         }
+        frame
     }
 
-    fn recursive_remove(&mut self) -> usize {
+    fn recursive_remove(&mut self, ctx: &Context) -> usize {
+        let waves = self.removal_waves();
+        if ctx.is_observed() {
+            let last_wave = waves.iter().flatten().flatten().max().copied().unwrap_or(0);
+            for wave in 0..=last_wave {
+                ctx.emit(|| Event::Grid(self.wave_frame(&waves, wave)));
+            }
+        }
+        if ctx.logs(Level::Explain) {
+            let mut removed: Vec<usize> = Vec::new();
+            for &wave in waves.iter().flatten().flatten() {
+                if removed.len() < wave {
+                    removed.resize(wave, 0);
+                }
+                removed[wave - 1] += 1;
+            }
+            for (i, count) in removed.iter().enumerate() {
+                ctx.explain(|| format!("Wave {}: {count} rolls removed", i + 1));
+            }
+        }
         let mut res = 0;
-        while let Some(x) = self.remove_reachables() {
-            res += x;
+        for (line, waves) in self.0.iter_mut().zip(waves) {
+            for (cell, wave) in line.iter_mut().zip(waves) {
+                if wave.is_some() {
+                    *cell = false;
+                    res += 1;
+                }
+            }
         }
         res
     }
 }
 
 impl FromStr for Map {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Map(s
-            .lines()
-            .map(|line| line.chars().map(|c| c == '@').collect())
-            .collect()))
+        s.lines()
+            .map(|line| {
+                line.char_indices()
+                    .map(|(i, c)| match c {
+                        '@' => Ok(true),
+                        '.' => Ok(false),
+                        _ => Err(ParseError::at(
+                            s,
+                            &line[i..i + c.len_utf8()],
+                            format!("Expected @ or ., found {c:?}"),
+                        )),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()
+            .map(Map)
     }
 }
 
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
-        let map: Map = input.parse().expect("Couldn't parse Map");
+    fn part1(&self, input: &str, _ctx: &Context) -> Option<SolvedValue> {
+        let map: Map = parse::or_report(input.parse(), input);
         Some(map.reachable_roll_count().into())
     }
 
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
-        let mut map: Map = input.parse().expect("Couldn't parse Map");
-        Some(map.recursive_remove().into())
+    fn part2(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let mut map: Map = parse::or_report(input.parse(), input);
+        Some(map.recursive_remove(ctx).into())
+    }
+
+    fn validate(&self, input: &str, _ctx: &Context) -> Vec<Violation> {
+        let map: Map = match input.parse() {
+            Ok(map) => map,
+            Err(e) => return vec![Violation::malformed(input, &e)],
+        };
+        if map.0.iter().all(Vec::is_empty) {
+            return vec![Violation::new(
+                "The grid has cells",
+                "There are no cells to place rolls in",
+            )];
+        }
+        Vec::new()
+    }
+
+    /// A square grid `size` cells wide.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        let size = size.max(1);
+        let rows: Vec<String> = (0..size)
+            .map(|_| {
+                (0..size)
+                    .map(|_| if rng.chance(3, 5) { '@' } else { '.' })
+                    .chain(['\n'])
+                    .collect()
+            })
+            .collect();
+        Some(rows.concat())
     }
 }
 
@@ -123,29 +234,69 @@ impl Solution for Day {
 mod tests {
     use super::*;
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 4;
 
+    #[test]
+    fn test_removal_waves_match_rounds() {
+        for input in ["@@@@@@@\n@@@@@@@\n@@@.@@@", "@@\n@@\n@@\n@@\n@@\n@@"] {
+            let mut map: Map = input.parse().unwrap();
+            let waves = map.removal_waves();
+            let mut wave = 0;
+            while map.reachable_roll_count() > 0 {
+                wave += 1;
+                let reachables: Vec<Position> = map
+                    .rolls()
+                    .filter(|&pos| map.number_of_neighbour_rolls(pos) < MAX_REACHABLE_NEIGHBOURS)
+                    .collect();
+                for pos in reachables {
+                    assert_eq!(waves[pos.row][pos.col], Some(wave));
+                    map.0[pos.row][pos.col] = false;
+                }
+            }
+            assert!(map.rolls().all(|pos| waves[pos.row][pos.col].is_none()));
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let ctx = Context::default();
+        assert_eq!(Day.validate("@.@@\n.@..\n", &ctx), vec![]);
+        assert_eq!(
+            Day.validate("\n\n", &ctx)[0].assumption,
+            "The grid has cells"
+        );
+        assert!(
+            Day.validate("@.\n.x\n", &ctx)[0]
+                .detail
+                .contains("line 2, column 2")
+        );
+    }
+
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(13.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(13.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(1547.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(1547.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(43.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(43.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(8948.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(8948.into()));
     }
 }
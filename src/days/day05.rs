@@ -1,6 +1,11 @@
-use std::{ops::RangeInclusive, str::FromStr};
+use std::{fmt::Write, ops::RangeInclusive, str::FromStr};
 
-use crate::solution::{Solution, SolvedValue};
+use crate::{
+    bytes,
+    generate::Rng,
+    parse::{self, ParseError},
+    solution::{Context, Solution, SolvedValue},
+};
 
 fn do_ranges_overlap(range1: &RangeInclusive<usize>, range2: &RangeInclusive<usize>) -> bool {
     !(range1.end() < range2.start() || range2.end() < range1.start())
@@ -37,7 +42,12 @@ fn merge_overlapping_ranges(ranges: &[RangeInclusive<usize>]) -> Vec<RangeInclus
     merged_ranges
 }
 
-struct Database {
+/// Number of ids in `range`, which is one more than fits into a `usize` for `0..=usize::MAX`.
+fn range_len(range: &RangeInclusive<usize>) -> u128 {
+    (range.end() - range.start()) as u128 + 1
+}
+
+pub(crate) struct Database {
     fresh: Vec<RangeInclusive<usize>>,
     items: Vec<usize>,
 }
@@ -50,35 +60,39 @@ impl Database {
             .count()
     }
 
-    fn possible_items_in_fresh_ranges_count(&self) -> usize {
-        merge_overlapping_ranges(&self.fresh)
-            .iter()
-            .map(|r| r.end() - r.start() + 1)
-            .sum::<usize>()
+    fn possible_items_in_fresh_ranges_count(&self, ctx: &Context) -> u128 {
+        let merged = merge_overlapping_ranges(&self.fresh);
+        ctx.explain(|| {
+            format!(
+                "{} fresh ranges merged into {}",
+                self.fresh.len(),
+                merged.len()
+            )
+        });
+        for range in &merged {
+            ctx.explain(|| {
+                format!(
+                    "{}-{} ({} ids)",
+                    range.start(),
+                    range.end(),
+                    range_len(range)
+                )
+            });
+        }
+        merged.iter().map(range_len).sum()
     }
 }
 
 impl FromStr for Database {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (ranges, items) = s.split_once("\n\n").ok_or(())?;
-        let fresh = ranges
-            .lines()
-            .filter_map(|line| {
-                if let Some((start_str, end_str)) = line.split_once('-') {
-                    let start = start_str.trim().parse().ok()?;
-                    let end = end_str.trim().parse().ok()?;
-                    Some(start..=end)
-                } else {
-                    None
-                }
-            })
-            .collect();
-        let items = items
-            .lines()
-            .filter_map(|line| line.trim().parse().ok())
-            .collect();
+        let (ranges, items) = s.split_once("\n\n").ok_or_else(|| {
+            ParseError::at_end(s, "Expected an empty line between fresh ranges and items")
+        })?;
+        let text = s.as_bytes();
+        let fresh = bytes::parse_lines(ranges.as_bytes(), |line| bytes::range(text, line))?;
+        let items = bytes::parse_lines(items.as_bytes(), |line| bytes::number(text, line))?;
         Ok(Database { fresh, items })
     }
 }
@@ -86,48 +100,116 @@ impl FromStr for Database {
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
-        Some(input.parse::<Database>().ok()?.count_fresh_items().into())
+    fn part1(&self, input: &str, _ctx: &Context) -> Option<SolvedValue> {
+        let database: Database = parse::or_report(input.parse(), input);
+        Some(database.count_fresh_items().into())
     }
 
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
-        Some(
-            input
-                .parse::<Database>()
-                .ok()?
-                .possible_items_in_fresh_ranges_count()
-                .into(),
-        )
+    fn part2(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let database: Database = parse::or_report(input.parse(), input);
+        Some(SolvedValue::wide(
+            database.possible_items_in_fresh_ranges_count(ctx),
+        ))
+    }
+
+    /// `size` fresh ranges that may overlap and `size` items, about half of them fresh.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        let fresh: Vec<(usize, usize)> = (0..size)
+            .map(|_| {
+                let start = rng.range(1..=500_000_000_000_000);
+                (start, start + rng.range(0..=1_000_000_000_000))
+            })
+            .collect();
+        let mut input = String::new();
+        for (start, end) in &fresh {
+            let _ = writeln!(input, "{start}-{end}");
+        }
+        input.push('\n');
+        for _ in 0..size {
+            let item = match fresh.get(rng.range(0..=2 * size)) {
+                Some(&(start, end)) => rng.range(start..=end),
+                None => rng.range(1..=500_000_000_000_000),
+            };
+            let _ = writeln!(input, "{item}");
+        }
+        Some(input)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::property::{check, equal};
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 5;
 
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(3.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(3.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(690.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(690.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(14.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(14.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(344_323_629_240_733.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(344_323_629_240_733.into()));
+    }
+
+    #[test]
+    fn test_malformed_lines_are_reported() {
+        let input = "3-5\n10-1x\n\n1\n";
+        let error = input.parse::<Database>().err().unwrap();
+        assert_eq!(
+            error.render(input),
+            "error: Expected a number, found \"1x\"\n --> line 2, column 4\n  |\n2 | 10-1x\n  |    ^^"
+        );
+    }
+
+    #[test]
+    fn test_merge_preserves_union_and_is_disjoint() {
+        // Ranges as (start, length)
+        check(
+            |rng| {
+                (0..rng.range(0..=8))
+                    .map(|_| (rng.range(0..=100), rng.range(0..=20)))
+                    .collect::<Vec<_>>()
+            },
+            |ranges| {
+                let ranges: Vec<_> = ranges
+                    .iter()
+                    .map(|&(start, length)| start..=start + length)
+                    .collect();
+                let merged = merge_overlapping_ranges(&ranges);
+                for id in 0..=130 {
+                    equal(
+                        &merged.iter().any(|range| range.contains(&id)),
+                        &ranges.iter().any(|range| range.contains(&id)),
+                    )
+                    .map_err(|e| format!("Id {id}: {e}"))?;
+                }
+                for (i, a) in merged.iter().enumerate() {
+                    if let Some(b) = merged[i + 1..].iter().find(|b| do_ranges_overlap(a, b)) {
+                        return Err(format!("{a:?} and {b:?} overlap in {merged:?}"));
+                    }
+                }
+                Ok(())
+            },
+        );
     }
 }
@@ -1,82 +1,167 @@
-use crate::solution::{Solution, SolvedValue};
+use std::ops::Range;
 
+use crate::{
+    expression::{self, Operator},
+    generate::Rng,
+    layout::Layout,
+    parse::{self, ParseError},
+    solution::{Context, Solution, SolvedValue},
+};
+
+/// A problem of the worksheet: its numbers combined by its operator.
 #[derive(Debug)]
-enum Equation {
-    Add(Vec<usize>),
-    Multiply(Vec<usize>),
+struct Equation<'a> {
+    operator: Operator,
+    /// The operator as written in the input, to point errors at
+    symbol: &'a str,
+    columns: Range<usize>,
+    numbers: Vec<i128>,
 }
 
-impl Equation {
-    fn execute(&self) -> usize {
-        match self {
-            Equation::Add(numbers) => numbers.iter().sum(),
-            Equation::Multiply(numbers) => numbers.iter().product(),
+impl Equation<'_> {
+    /// The columns of the problem, counted from 1 like in error messages.
+    fn describe_columns(&self) -> String {
+        let Range { start, end } = self.columns;
+        if end - start == 1 {
+            format!("column {end}")
+        } else {
+            format!("columns {}-{end}", start + 1)
         }
     }
 }
 
-fn parse_input(input: &str) -> Vec<Equation> {
-    let lines: Vec<Vec<&str>> = input
-        .lines()
-        .map(|line| line.split_whitespace().collect())
-        .collect();
-    let (operators, number_lines) = lines.split_last().expect("Line missing");
-
-    operators
-        .iter()
-        .enumerate()
-        .map(|(index, op)| {
-            let numbers = number_lines
-                .iter()
-                .filter_map(|number_line| number_line[index].trim().parse().ok())
-                .collect();
-            match *op {
-                "+" => Equation::Add(numbers),
-                "*" => Equation::Multiply(numbers),
-                _ => panic!("Unknown operator"),
+/// Sum of the results of all problems, giving up on the input with every problem that has no
+/// result, or if the sum doesn't fit into an `i128`.
+fn grand_total(input: &str, equations: &[Equation], ctx: &Context) -> i128 {
+    let mut total = Some(0_i128);
+    let mut failures = Vec::new();
+    for equation in equations {
+        match expression::evaluate(equation.operator, &equation.numbers) {
+            Ok(result) => {
+                ctx.explain(|| {
+                    let numbers: Vec<String> =
+                        equation.numbers.iter().map(ToString::to_string).collect();
+                    format!(
+                        "Problem in {}: {} = {result}",
+                        equation.describe_columns(),
+                        numbers.join(&format!(" {} ", equation.operator))
+                    )
+                });
+                total = total.and_then(|total| total.checked_add(result));
             }
-        })
-        .collect()
+            Err(e) => {
+                let message = format!(
+                    "The problem in {} can't be solved, as {e}",
+                    equation.describe_columns()
+                );
+                failures.push(ParseError::at(input, equation.symbol, message).render(input));
+            }
+        }
+    }
+    if !failures.is_empty() {
+        parse::reject(failures.join("\n"));
+    }
+    total.unwrap_or_else(|| parse::reject("The grand total doesn't fit into 128 bits"))
 }
 
-fn parse_transposed_input(input: &str) -> Vec<Equation> {
-    let mut lines: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
-    let cols = lines[0].len();
-    let mut transposed: Vec<String> = vec![String::with_capacity(lines.len()); cols];
-    for col in &mut transposed {
-        for line in &mut lines {
-            col.push(line.remove(0));
-        }
+fn parse_operator<'a>(
+    input: &str,
+    symbol: &'a str,
+    columns: Range<usize>,
+    numbers: Vec<i128>,
+) -> Result<Equation<'a>, ParseError> {
+    let operator = Operator::from_symbol(symbol).ok_or_else(|| {
+        let symbols: Vec<&str> = Operator::ALL.iter().map(|op| op.symbol()).collect();
+        ParseError::at(
+            input,
+            symbol,
+            format!(
+                "Expected an operator out of {} < >, found {symbol:?}",
+                symbols.join(" ")
+            ),
+        )
+    })?;
+    Ok(Equation {
+        operator,
+        symbol,
+        columns,
+        numbers,
+    })
+}
+
+/// The worksheet as blocks of columns, one per problem, with the operators in the last line.
+fn worksheet(input: &str) -> Result<Layout<'_>, ParseError> {
+    let layout = Layout::new(input);
+    if layout.lines().is_empty() {
+        return Err(ParseError::at_end(input, "Expected a line of operators"));
     }
+    Ok(layout)
+}
 
-    let mut groups = vec![];
-    let mut current_group = vec![];
+/// The problems with their numbers read row by row.
+fn parse_input(input: &str) -> Result<Vec<Equation<'_>>, ParseError> {
+    let layout = worksheet(input)?;
+    let operators = layout.lines().len() - 1;
+    layout
+        .blocks()
+        .map(|block| {
+            let operator = block.row(operators).trim();
+            let numbers = (0..operators)
+                .map(|row| {
+                    let number = block.row(row);
+                    if number.trim().is_empty() {
+                        return Err(ParseError::at(
+                            input,
+                            number,
+                            format!("Expected a number for operator {operator}"),
+                        ));
+                    }
+                    parse::number(input, number)
+                })
+                .collect::<Result<_, _>>()?;
+            parse_operator(input, operator, block.columns(), numbers)
+        })
+        .collect()
+}
 
-    for s in &mut transposed {
-        *s = s.trim().to_string();
-        if s.is_empty() {
-            if !current_group.is_empty() {
-                groups.push(current_group);
-                current_group = vec![];
+/// The number written top to bottom in `cells`, `None` if there is anything but digits between
+/// the blanks around it or it doesn't fit into an `i128`.
+fn column_number(cells: impl Iterator<Item = u8>) -> Option<i128> {
+    let mut number: Option<i128> = None;
+    let mut ended = false;
+    for cell in cells {
+        match cell {
+            b' ' => ended = number.is_some(),
+            b'0'..=b'9' if !ended => {
+                let digit = i128::from(cell - b'0');
+                number = Some(number.unwrap_or(0).checked_mul(10)?.checked_add(digit)?);
             }
-        } else {
-            current_group.push(s.clone());
+            _ => return None,
         }
     }
-    if !current_group.is_empty() {
-        groups.push(current_group);
-    }
+    number
+}
 
-    groups
-        .iter_mut()
-        .map(|group| {
-            let group_type = group[0].pop().expect("Group cannot be empty");
-            let numbers: Vec<usize> = group.iter().filter_map(|s| s.trim().parse().ok()).collect();
-            if group_type == '+' {
-                Equation::Add(numbers)
-            } else {
-                Equation::Multiply(numbers)
-            }
+/// The problems with their numbers read column by column.
+fn parse_transposed_input(input: &str) -> Result<Vec<Equation<'_>>, ParseError> {
+    let layout = worksheet(input)?;
+    let operators = layout.lines().len() - 1;
+    layout
+        .blocks()
+        .map(|block| {
+            let numbers = block
+                .read_columns(0..operators)
+                .map(|(col, cells)| {
+                    column_number(cells).ok_or_else(|| {
+                        ParseError::at(
+                            input,
+                            layout.cell(0, col),
+                            format!("Expected a number in column {}", col + 1),
+                        )
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+            parse_operator(input, block.row(operators).trim(), block.columns(), numbers)
         })
         .collect()
 }
@@ -84,54 +169,123 @@ fn parse_transposed_input(input: &str) -> Vec<Equation> {
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
-        Some(
-            parse_input(input)
-                .iter()
-                .map(Equation::execute)
-                .sum::<usize>()
-                .into(),
-        )
+    fn part1(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        Some(SolvedValue::signed(grand_total(
+            input,
+            &parse::or_report(parse_input(input), input),
+            ctx,
+        )))
     }
 
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
-        Some(
-            parse_transposed_input(input)
-                .iter()
-                .map(Equation::execute)
-                .sum::<usize>()
-                .into(),
-        )
+    fn part2(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        Some(SolvedValue::signed(grand_total(
+            input,
+            &parse::or_report(parse_transposed_input(input), input),
+            ctx,
+        )))
+    }
+
+    /// A worksheet with `size` problems.
+    ///
+    /// Numbers of a problem are aligned to the same side and sorted by length, so reading them
+    /// column by column never finds a gap between the digits.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        let rows = rng.range(2..=4);
+        let mut lines = vec![Vec::new(); rows + 1];
+        for _ in 0..size.max(1) {
+            let width = rng.range(1..=4);
+            let mut lengths: Vec<usize> = (0..rows).map(|_| rng.range(1..=width)).collect();
+            lengths[0] = width;
+            lengths.sort_unstable();
+            if rng.chance(1, 2) {
+                lengths.reverse();
+            }
+            let left = rng.chance(1, 2);
+            for (line, length) in lines.iter_mut().zip(lengths) {
+                let number = rng.digits(length);
+                line.push(if left {
+                    format!("{number:<width$}")
+                } else {
+                    format!("{number:>width$}")
+                });
+            }
+            let operator = if rng.chance(1, 2) { '+' } else { '*' };
+            lines[rows].push(format!("{operator:<width$}"));
+        }
+        Some(lines.iter().map(|line| line.join(" ") + "\n").collect())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parse::Location;
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 6;
 
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(4_277_556.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(4_277_556.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(5_977_759_036_837.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(5_977_759_036_837.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(3_263_827.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(3_263_827.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(9_630_000_828_442.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(9_630_000_828_442.into()));
+    }
+
+    #[test]
+    fn test_ragged_worksheet() {
+        // The example without trailing spaces and with an operator that isn't in the first column
+        let input = "123 328  51 64\n 45 64  387 23\n  6 98  215 314\n*   +    *   +\n";
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(input, &ctx), Some(4_277_556.into()));
+        assert_eq!(Day.part2(input, &ctx), Some(3_263_827.into()));
+        let input = "1 2\n+ x\n";
+        let error = parse_transposed_input(input).err().unwrap();
+        assert_eq!(error.location(input), Location { line: 2, column: 3 });
+    }
+
+    #[test]
+    fn test_operators() {
+        let input = "20 107 9 12\n 3  10 2  5\n-  max ^ % \n";
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(input, &ctx), Some((17 + 107 + 81 + 2).into()));
+        assert_eq!(Day.part2(input, &ctx), Some((70 + 92 + 1 - 1).into()));
+        assert_eq!(Day.part2("1 \n 9\n- \n", &ctx), Some("-8".into()));
+        let failing = "1 10 5\n2  0 0\n+  / %\n";
+        let failure = std::panic::catch_unwind(|| {
+            grand_total(failing, &parse_input(failing).unwrap(), &Context::default())
+        })
+        .unwrap_err();
+        let message = failure.downcast_ref::<String>().unwrap();
+        // Every failing problem is reported, not just the first one
+        assert!(
+            message.contains("The problem in columns 3-4 can't be solved, as it divides by zero"),
+            "{message}"
+        );
+        assert!(
+            message.contains("The problem in column 6 can't be solved, as it divides by zero"),
+            "{message}"
+        );
+        assert_eq!(SolvedValue::signed(-8), SolvedValue::String("-8".into()));
+        assert!(parse_input("1\n?\n").is_err());
     }
 }
@@ -1,151 +1,224 @@
-use std::{
-    collections::{HashSet, VecDeque},
-    fmt::Display,
-    str::FromStr,
-};
-
-use crate::solution::{Solution, SolvedValue};
+use std::collections::{HashSet, VecDeque};
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
-struct Position {
-    col: usize,
-    row: usize,
-}
+use crate::{
+    animation::{Colour, Frame},
+    beam::{BeamError, BeamGrid, BeamReport, Beams, Position, Rules},
+    generate::Rng,
+    observe::Event,
+    parse,
+    solution::{Context, Implementation, Part, Solution, SolvedValue, Violation},
+};
 
-#[derive(Debug)]
-struct Map {
-    start: Position,
-    splitters: Vec<Vec<bool>>,
+fn parse_input(input: &str) -> BeamGrid {
+    BeamGrid::parse(input, &Rules::default()).unwrap_or_else(|e| parse::reject(e))
 }
 
-impl Map {
-    fn number_of_splits(&self) -> usize {
-        let mut queue = VecDeque::from(vec![self.start]);
-        let mut visited = HashSet::new();
-        let mut splits = 0;
-
-        while let Some(item) = queue.pop_front() {
-            if !visited.contains(&item) {
-                visited.insert(item);
-                if item.row < self.splitters.len() {
-                    if self.is_splitter_below(item) {
-                        splits += 1;
-                        queue.push_back(Position {
-                            col: item.col - 1,
-                            row: item.row + 1,
-                        });
-                        queue.push_back(Position {
-                            col: item.col + 1,
-                            row: item.row + 1,
-                        });
-                    } else {
-                        queue.push_back(Position {
-                            row: item.row + 1,
-                            col: item.col,
-                        });
-                    }
-                }
+/// Draws the beams in the grid, with the cells earlier beams passed in `trail`.
+fn beam_frame(grid: &BeamGrid, beams: &Beams, trail: &HashSet<Position>, step: usize) -> Frame {
+    let current: HashSet<Position> = beams.keys().map(|(position, _)| *position).collect();
+    let timelines: u128 = beams.values().sum();
+    let mut frame = Frame::new(format!(
+        "Step {step}: {} beams standing for {timelines} timelines",
+        current.len()
+    ));
+    for (row, line) in grid.tiles().iter().enumerate() {
+        frame.push_line(line.iter().enumerate().map(|(col, &tile)| {
+            let position = Position { row, col };
+            if current.contains(&position) {
+                ('|', Some(Colour::Yellow))
+            } else if trail.contains(&position) {
+                ('|', Some(Colour::Grey))
+            } else if tile == '^' {
+                (tile, Some(Colour::Red))
+            } else {
+                (tile, None)
             }
-        }
-        splits
-    }
-    fn is_splitter_at(&self, pos: Position) -> bool {
-        self.splitters
-            .get(pos.row)
-            .unwrap_or(&vec![])
-            .get(pos.col)
-            .unwrap_or(&false)
-            .to_owned()
+        }));
     }
-    fn is_splitter_below(&self, pos: Position) -> bool {
-        self.is_splitter_at(Position {
-            col: pos.col,
-            row: pos.row + 1,
-        })
+    frame
+}
+
+/// Simulates the beams while showing one frame per step, i.e. per row for a beam moving down.
+fn simulate_animated(grid: &BeamGrid, ctx: &Context) -> Result<BeamReport, BeamError> {
+    if !ctx.is_observed() {
+        return grid.simulate();
     }
+    let mut trail = HashSet::new();
+    let mut step = 0;
+    grid.simulate_with(|beams| {
+        step += 1;
+        ctx.emit(|| Event::Grid(beam_frame(grid, beams, &trail, step)));
+        trail.extend(beams.keys().map(|(position, _)| *position));
+    })
+}
 
-    fn number_of_timeline_splits(&self) -> usize {
-        let mut possible_paths = vec![vec![0usize; self.splitters[0].len()]; self.splitters.len()];
-        possible_paths[self.start.row][self.start.col] = 1;
-        for row in 1..self.splitters.len() {
-            for col in 0..self.splitters[row].len() {
-                if self.is_splitter_at(Position { col, row }) {
-                    possible_paths[row][col - 1] += possible_paths[row - 1][col];
-                    possible_paths[row][col + 1] += possible_paths[row - 1][col];
-                } else {
-                    possible_paths[row][col] += possible_paths[row - 1][col];
-                }
+/// Row and column of the first start tile, searching row by row.
+fn start(tiles: &[Vec<char>]) -> (usize, usize) {
+    tiles
+        .iter()
+        .enumerate()
+        .find_map(|(row, line)| Some((row, line.iter().position(|&tile| tile == 'S')?)))
+        .expect("Grid was parsed with a start tile")
+}
+
+/// Part 1 without the beam engine: a breadth first search over the cells beams reach.
+fn search_part1(input: &str) -> SolvedValue {
+    let grid = parse_input(input);
+    let tiles = grid.tiles();
+    let is_splitter =
+        |row: usize, col: usize| tiles.get(row).and_then(|line| line.get(col)) == Some(&'^');
+    let mut queue = VecDeque::from([start(tiles)]);
+    let mut visited = HashSet::new();
+    let mut splitters = HashSet::new();
+    while let Some((row, col)) = queue.pop_front() {
+        if row + 1 >= tiles.len() || !visited.insert((row, col)) {
+            continue;
+        }
+        if is_splitter(row + 1, col) {
+            splitters.insert((row + 1, col));
+            if let Some(left) = col.checked_sub(1) {
+                queue.push_back((row + 1, left));
+            }
+            if col + 1 < tiles[row + 1].len() {
+                queue.push_back((row + 1, col + 1));
             }
+        } else {
+            queue.push_back((row + 1, col));
         }
-        possible_paths[self.splitters.len() - 1].iter().sum()
     }
+    splitters.len().into()
 }
 
-impl Display for Map {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (row, line) in self.splitters.iter().enumerate() {
-            for (col, is_splitter) in line.iter().enumerate() {
-                let curr_pos = Position { col, row };
-                if self.start == curr_pos {
-                    write!(f, "S")?;
-                } else if *is_splitter {
-                    write!(f, "^")?;
-                } else {
-                    write!(f, ".")?;
+/// Part 2 without the beam engine: timelines per column, one row after the other.
+fn rows_part2(input: &str, _ctx: &Context) -> Option<SolvedValue> {
+    let grid = parse_input(input);
+    let tiles = grid.tiles();
+    let (start_row, start_col) = start(tiles);
+    let mut timelines = vec![0u128; tiles[start_row].len()];
+    timelines[start_col] = 1;
+    // Timelines whose beam left the grid at a side
+    let mut exited = 0u128;
+    for line in &tiles[start_row + 1..] {
+        let mut next = vec![0u128; line.len()];
+        for (col, &count) in timelines.iter().enumerate() {
+            let mut send = |target: Option<usize>| {
+                match target {
+                    Some(target) => next[target] = next[target].checked_add(count)?,
+                    None => exited = exited.checked_add(count)?,
                 }
+                Some(())
+            };
+            match line.get(col) {
+                None => send(None)?,
+                Some('^') => {
+                    send(col.checked_sub(1))?;
+                    send(Some(col + 1).filter(|&right| right < line.len()))?;
+                }
+                Some(_) => send(Some(col))?,
             }
-            writeln!(f)?;
         }
-        Ok(())
+        timelines = next;
     }
+    Some(SolvedValue::wide(
+        timelines
+            .iter()
+            .try_fold(exited, |sum, &count| sum.checked_add(count))?,
+    ))
 }
 
-impl FromStr for Map {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut start = Position { col: 0, row: 0 };
-        let mut splitters = vec![];
-        for (row, line) in s.lines().enumerate() {
-            let mut line_splitters = vec![];
-            for (col, c) in line.trim().chars().enumerate() {
-                line_splitters.push(match c {
-                    'S' => {
-                        start = Position { col, row };
-                        false
-                    }
-                    '^' => true,
-                    _ => false,
-                });
-            }
-            splitters.push(line_splitters);
-        }
-
-        Ok(Map { start, splitters })
-    }
+fn explain_report(ctx: &Context, report: &BeamReport) {
+    ctx.explain(|| format!("{} splitters are hit by a beam", report.splits));
+    ctx.explain(|| {
+        format!(
+            "{} timelines: {} leave the manifold, {} are absorbed, {} are blocked",
+            report.timelines, report.exited, report.absorbed, report.blocked
+        )
+    });
 }
 
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
-        Some(
-            input
-                .parse::<Map>()
-                .expect("Unable to parse map")
-                .number_of_splits()
-                .into(),
-        )
+    fn part1(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let report = parse_input(input)
+            .simulate()
+            .unwrap_or_else(|e| parse::reject(e));
+        explain_report(ctx, &report);
+        Some(report.splits.into())
     }
 
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
-        Some(
-            input
-                .parse::<Map>()
-                .expect("Unable to parse map")
-                .number_of_timeline_splits()
-                .into(),
-        )
+    fn part2(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let report =
+            simulate_animated(&parse_input(input), ctx).unwrap_or_else(|e| parse::reject(e));
+        explain_report(ctx, &report);
+        Some(SolvedValue::wide(report.timelines))
+    }
+
+    fn validate(&self, input: &str, _ctx: &Context) -> Vec<Violation> {
+        let violation = |e: BeamError| {
+            let assumption = match e {
+                BeamError::MissingStart | BeamError::MultipleStarts => {
+                    "The manifold has exactly one start"
+                }
+                BeamError::UnknownTile { .. } => "The input is well-formed",
+                BeamError::Overflow => "The number of timelines fits into 128 bits",
+                BeamError::LeftGrid { .. } | BeamError::Loop => "Beams leave the manifold",
+            };
+            vec![Violation::new(assumption, e.to_string())]
+        };
+        match BeamGrid::parse(input, &Rules::default()).and_then(|grid| grid.simulate()) {
+            Ok(_) => Vec::new(),
+            Err(e) => violation(e),
+        }
+    }
+
+    fn implementations(&self) -> &'static [Implementation] {
+        &[
+            Implementation {
+                name: "search",
+                part: Part::One,
+                solve: |input, _ctx| Some(search_part1(input)),
+            },
+            Implementation {
+                name: "rows",
+                part: Part::Two,
+                solve: rows_part2,
+            },
+        ]
+    }
+
+    /// A manifold with `size` rows of splitters spreading out from the start like a pyramid.
+    ///
+    /// Splitters that would push the number of timelines past 2^100 are left out, so counting them
+    /// never overflows.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        const MAX_TIMELINES: u128 = 1 << 100;
+        let width = 2 * size + 3;
+        let start = size + 1;
+        let mut rows = vec![format!("{:.<width$}", format!("{:.<start$}S", ""))];
+        // Timelines of the beams in every column and all together
+        let mut beams = vec![0u128; width];
+        beams[start] = 1;
+        let mut timelines = 1;
+        for level in 0..size {
+            rows.push(".".repeat(width));
+            let mut row = vec!['.'; width];
+            let mut next = beams.clone();
+            for col in (start - level..=start + level).step_by(2) {
+                let splits = beams[col];
+                if (level == 0 || rng.chance(3, 4)) && timelines + splits <= MAX_TIMELINES {
+                    row[col] = '^';
+                    next[col] = 0;
+                    next[col - 1] += splits;
+                    next[col + 1] += splits;
+                    timelines += splits;
+                }
+            }
+            beams = next;
+            rows.push(row.into_iter().collect());
+        }
+        rows.push(".".repeat(width));
+        Some(rows.join("\n") + "\n")
     }
 }
 
@@ -153,29 +226,46 @@ impl Solution for Day {
 mod tests {
     use super::*;
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 7;
 
+    #[test]
+    fn test_validate() {
+        let ctx = Context::default();
+        // Splitters on the edges send one of their beams out of the side
+        assert_eq!(Day.validate("S..\n...\n^..\n", &ctx), vec![]);
+        let violations = Day.validate("...\n.^.\n", &ctx);
+        assert_eq!(
+            violations[0].assumption,
+            "The manifold has exactly one start"
+        );
+        assert_eq!(violations[0].detail, "No start tile");
+    }
+
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(21.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(21.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(1687.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(1687.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(40.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(40.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(390_684_413_472_684.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(390_684_413_472_684.into()));
     }
 }
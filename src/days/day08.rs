@@ -1,13 +1,20 @@
 use std::{
     cmp::{Ordering, Reverse},
-    collections::{BinaryHeap, HashSet},
-    str::FromStr,
+    collections::{BinaryHeap, HashMap, HashSet},
+    fmt::{Display, Write},
 };
 
-use crate::solution::{Solution, SolvedValue};
+use crate::{
+    bytes,
+    generate::Rng,
+    graph::Graph,
+    observe::Level,
+    parse::{self, ParseError},
+    solution::{Context, Parameter, Solution, SolvedValue, Violation, parses_as},
+};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
-struct Position {
+pub(crate) struct Position {
     x: usize,
     y: usize,
     z: usize,
@@ -20,26 +27,34 @@ impl Position {
             self.y.abs_diff(other.y),
             self.z.abs_diff(other.z),
         );
-        (dx * dx + dy * dy + dz * dz).isqrt()
+        let squared = |d: usize| (d as u128) * (d as u128);
+        let root = (squared(dx) + squared(dy) + squared(dz)).isqrt();
+        usize::try_from(root).expect("Coordinates below 2^63 are less than 2^64 apart")
     }
 }
 
-impl FromStr for Position {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s
-            .splitn(3, ',')
-            .filter_map(|s| s.parse().ok())
-            .collect::<Vec<usize>>()
-            .as_slice()
-        {
-            &[x, y, z] => Ok(Self { x, y, z }),
-            _ => Err(()),
-        }
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{}", self.x, self.y, self.z)
     }
 }
 
+/// Parses a position like `162,817,812` in `line` of `text`.
+fn parse_position(text: &[u8], line: &[u8]) -> Result<Position, ParseError> {
+    let (x, rest) = bytes::split_once(text, line, b',')?;
+    let (y, z) = bytes::split_once(text, rest, b',')?;
+    Ok(Position {
+        x: bytes::number(text, x)?,
+        y: bytes::number(text, y)?,
+        z: bytes::number(text, z)?,
+    })
+}
+
+pub(crate) fn parse_positions(input: &str) -> Result<Vec<Position>, ParseError> {
+    let text = input.as_bytes();
+    bytes::parse_lines(text, |line| parse_position(text, line))
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct Distance<'a> {
     a: &'a Position,
@@ -69,8 +84,31 @@ impl Ord for Distance<'_> {
     }
 }
 
+/// Junction boxes, numbered from 1, with a coordinate of 2^63 or more, too far out to measure
+/// distances in 64 bits.
+fn out_of_range(positions: &[Position]) -> impl Iterator<Item = (usize, &Position)> {
+    positions
+        .iter()
+        .enumerate()
+        .filter(|(_, position)| {
+            [position.x, position.y, position.z]
+                .iter()
+                .any(|&coordinate| coordinate >= 1 << 63)
+        })
+        .map(|(i, position)| (i + 1, position))
+}
+
 fn parse_input(input: &str) -> Vec<Position> {
-    input.lines().filter_map(|l| l.parse().ok()).collect()
+    let positions = parse::or_report(parse_positions(input), input);
+    if positions.is_empty() {
+        parse::reject("There are no junction boxes");
+    }
+    if let Some((box_number, position)) = out_of_range(&positions).next() {
+        parse::reject(format!(
+            "Junction box {box_number} is at {position}, coordinates have to be below 2^63"
+        ));
+    }
+    positions
 }
 
 fn build_distance_heap(positions: &[Position]) -> BinaryHeap<Reverse<Distance<'_>>> {
@@ -85,73 +123,180 @@ fn build_distance_heap(positions: &[Position]) -> BinaryHeap<Reverse<Distance<'_
     res
 }
 
+/// Wires up the given pairs of junction boxes.
+///
+/// Every connection is added in both directions, so the circuits are exactly the strongly connected
+/// components of the graph.
+fn connect<'a>(positions: &'a [Position], connections: &[Distance<'a>]) -> Graph<&'a Position> {
+    let mut circuits = Graph::new();
+    for position in positions {
+        circuits.intern(position);
+    }
+    for connection in connections {
+        let (a, b) = (circuits.intern(connection.a), circuits.intern(connection.b));
+        circuits.add_edge(a, b);
+        circuits.add_edge(b, a);
+    }
+    circuits
+}
+
+fn is_single_circuit(positions: &[Position], connections: &[Distance]) -> bool {
+    connect(positions, connections)
+        .reachable_from(0)
+        .into_iter()
+        .all(|reached| reached)
+}
+
+/// Logs how the `connections` merge circuits, in the order they are made.
+fn explain_merges(positions: &[Position], connections: &[Distance], ctx: &Context) {
+    if !ctx.logs(Level::Explain) && !ctx.logs(Level::Debug) {
+        return;
+    }
+    let index: HashMap<&Position, usize> = positions
+        .iter()
+        .enumerate()
+        .map(|(i, position)| (position, i))
+        .collect();
+    // Circuit of every box and the boxes of every circuit, always moving the smaller circuit
+    let mut circuit: Vec<usize> = (0..positions.len()).collect();
+    let mut members: Vec<Vec<usize>> = (0..positions.len()).map(|i| vec![i]).collect();
+    for (i, connection) in connections.iter().enumerate() {
+        let (a, b) = (circuit[index[connection.a]], circuit[index[connection.b]]);
+        if a == b {
+            ctx.debug(|| {
+                format!(
+                    "Connection {}: {} and {} are already in the same circuit",
+                    i + 1,
+                    connection.a,
+                    connection.b
+                )
+            });
+            continue;
+        }
+        let (larger, smaller) = if members[a].len() >= members[b].len() {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let moved = std::mem::take(&mut members[smaller]);
+        for &member in &moved {
+            circuit[member] = larger;
+        }
+        ctx.explain(|| {
+            format!(
+                "Connection {}: {} and {} merge circuits of {} and {} boxes",
+                i + 1,
+                connection.a,
+                connection.b,
+                members[larger].len(),
+                moved.len()
+            )
+        });
+        members[larger].extend(moved);
+    }
+}
+
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
+    fn part1(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
         let positions = parse_input(input);
         let mut distances = build_distance_heap(&positions);
-        let mut cliques: HashSet<Vec<Position>> = HashSet::new();
+        let connections: Vec<Distance> = (0..ctx.param::<usize>("connections"))
+            .map_while(|_| distances.pop().map(|distance| distance.0))
+            .collect();
+        explain_merges(&positions, &connections, ctx);
 
-        let max_iter = match positions.len() {
-            20 => 10,
-            _ => 1000,
-        };
+        let mut circuit_sizes: Vec<usize> = connect(&positions, &connections)
+            .strongly_connected_components()
+            .iter()
+            .map(Vec::len)
+            .collect();
+        circuit_sizes.sort_unstable();
+        Some(circuit_sizes.iter().rev().take(3).product::<usize>().into())
+    }
 
-        for _ in 0..max_iter {
-            let distance = distances.pop().expect("Distances Empty");
-            let mut new_clique: HashSet<Position> = HashSet::new();
-            new_clique.insert(*distance.0.a);
-            new_clique.insert(*distance.0.b);
-            cliques.retain(|c| {
-                if c.contains(distance.0.a) || c.contains(distance.0.b) {
-                    new_clique.extend(c);
-                    false
-                } else {
-                    true
-                }
-            });
-            cliques.insert(new_clique.into_iter().collect());
+    fn part2(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let positions = parse_input(input);
+        if positions.len() < 2 {
+            parse::reject("A single junction box has nothing to connect to");
         }
+        let mut distances = build_distance_heap(&positions);
 
-        let mut cliques = cliques.into_iter().collect::<Vec<Vec<Position>>>();
-        cliques.sort_by_key(Vec::len);
-        Some(
-            cliques
-                .iter()
-                .rev()
-                .take(3)
-                .map(Vec::len)
-                .product::<usize>()
-                .into(),
-        )
+        // Take twice as many connections until everything is connected, then binary search for the
+        // connection that joined the last two circuits.
+        let mut connections = Vec::new();
+        while !is_single_circuit(&positions, &connections) {
+            for _ in 0..connections.len().max(1) {
+                connections.push(distances.pop()?.0);
+            }
+        }
+        let (mut disconnected, mut connected) = (0, connections.len());
+        while connected - disconnected > 1 {
+            let mid = usize::midpoint(disconnected, connected);
+            if is_single_circuit(&positions, &connections[..mid]) {
+                connected = mid;
+            } else {
+                disconnected = mid;
+            }
+        }
+        let last = connections.get(connected.checked_sub(1)?)?;
+        explain_merges(&positions, &connections[..connected], ctx);
+        ctx.explain(|| {
+            format!(
+                "Connection {connected} between {} and {} joins the last two circuits",
+                last.a, last.b
+            )
+        });
+        Some(SolvedValue::wide(last.a.x as u128 * last.b.x as u128))
     }
 
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
-        let positions = parse_input(input);
-        let mut distances = build_distance_heap(&positions);
-        let mut cliques: HashSet<Vec<Position>> = HashSet::new();
-
-        let mut distance=distances.peek().unwrap().clone();
-
-        while cliques.len() != 1 || cliques.iter().next().map(Vec::len) != Some(positions.len()) {
-            distance = distances.pop().expect("Distances Empty");
-            let mut new_clique: HashSet<Position> = HashSet::new();
-            new_clique.insert(*distance.0.a);
-            new_clique.insert(*distance.0.b);
-            cliques.retain(|c| {
-                if c.contains(distance.0.a) || c.contains(distance.0.b) {
-                    new_clique.extend(c);
-                    false
-                } else {
-                    true
-                }
-            });
-            cliques.insert(new_clique.into_iter().collect());
+    fn validate(&self, input: &str, _ctx: &Context) -> Vec<Violation> {
+        let positions = match parse_positions(input) {
+            Ok(positions) => positions,
+            Err(e) => return vec![Violation::malformed(input, &e)],
+        };
+        let mut violations: Vec<Violation> = out_of_range(&positions)
+            .map(|(box_number, position)| {
+                Violation::new(
+                    "Coordinates are below 2^63",
+                    format!("Junction box {box_number} is at {position}"),
+                )
+            })
+            .collect();
+        if positions.len() < 2 {
+            violations.push(Violation::new(
+                "There are at least two junction boxes",
+                format!("Found {}", positions.len()),
+            ));
+        }
+        violations
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[Parameter {
+            name: "connections",
+            default: "1000",
+            description: "Number of closest junction box pairs to connect in part 1",
+            check: parses_as::<usize>,
+        }]
+    }
+
+    /// `size` (but at least two) junction boxes at distinct positions.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        let mut positions = HashSet::new();
+        let mut input = String::new();
+        while positions.len() < size.max(2) {
+            let position = Position {
+                x: rng.range(0..=99_999),
+                y: rng.range(0..=99_999),
+                z: rng.range(0..=99_999),
+            };
+            if positions.insert(position) {
+                let _ = writeln!(input, "{},{},{}", position.x, position.y, position.z);
+            }
         }
-        Some(
-            (distance.0.a.x * distance.0.b.x).into()
-        )
+        Some(input)
     }
 }
 
@@ -159,29 +304,58 @@ impl Solution for Day {
 mod tests {
     use super::*;
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 8;
 
+    #[test]
+    #[should_panic(expected = "There are no junction boxes")]
+    fn test_empty_input_is_rejected() {
+        Day.part1("\n", &Context::new(&Day, false));
+    }
+
+    #[test]
+    #[should_panic(expected = "A single junction box has nothing to connect to")]
+    fn test_single_box_is_rejected() {
+        Day.part2("1,2,3\n", &Context::new(&Day, false));
+    }
+
+    #[test]
+    fn test_validate() {
+        let ctx = Context::new(&Day, false);
+        assert_eq!(Day.validate("1,2,3\n4,5,6\n", &ctx), vec![]);
+        assert_eq!(
+            Day.validate("1,2,3\n", &ctx),
+            vec![Violation::new(
+                "There are at least two junction boxes",
+                "Found 1"
+            )]
+        );
+    }
+
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(40.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(40.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(171_503.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(171_503.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(25_272.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(25_272.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(9_069_509_600.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(9_069_509_600.into()));
     }
 }
@@ -1,193 +1,209 @@
-use crate::solution::{Solution, SolvedValue};
-
-fn parse_input(input: &str) -> Vec<Position> {
-    input
-        .lines()
-        .filter_map(|line| line.split_once(','))
-        .filter_map(|(a, b)| {
-            a.parse()
-                .and_then(|x| b.parse().map(|y| Position { x, y }))
-                .ok()
+use std::ops::RangeInclusive;
+
+use crate::{
+    bytes,
+    generate::Rng,
+    parse::{self, ParseError},
+    polygon::{InsideGrid, Point, Polygon, PolygonError},
+    solution::{Context, Solution, SolvedValue, Violation},
+    visualize::{SvgCanvas, Visualization},
+};
+
+const SVG_WIDTH: usize = 1000;
+
+pub(crate) fn parse_corners(input: &str) -> Result<Vec<Point>, ParseError> {
+    let text = input.as_bytes();
+    bytes::parse_lines(text, |line| {
+        let (x, y) = bytes::split_once(text, line, b',')?;
+        Ok(Point {
+            x: bytes::number(text, x)?,
+            y: bytes::number(text, y)?,
         })
-        .collect()
+    })
 }
 
-#[derive(Debug)]
-struct Compressor {
-    xs: Vec<usize>,
-    ys: Vec<usize>,
+fn parse_input(input: &str) -> Vec<Point> {
+    parse::or_report(parse_corners(input), input)
 }
 
-impl Compressor {
-    fn new(corners: &[Position]) -> Self {
-        let mut compressed_x = Vec::new();
-        let mut compressed_y = Vec::new();
-        for &Position { x, y } in corners {
-            compressed_x.push(x);
-            compressed_y.push(y);
-        }
-        compressed_x.sort_unstable();
-        compressed_x.dedup();
-        compressed_y.sort_unstable();
-        compressed_y.dedup();
-        Compressor {
-            xs: compressed_x,
-            ys: compressed_y,
+fn parse_polygon(input: &str) -> Polygon {
+    Polygon::new(parse_input(input)).unwrap_or_else(|e| {
+        parse::reject(format!(
+            "Red tiles don't form a simple rectilinear polygon: {e}"
+        ))
+    })
+}
+
+fn rectangle_area(a: Point, b: Point) -> usize {
+    let side = |from: usize, to: usize| from.abs_diff(to).checked_add(1);
+    side(a.x, b.x)
+        .zip(side(a.y, b.y))
+        .and_then(|(width, height)| width.checked_mul(height))
+        .unwrap_or_else(|| {
+            parse::reject(format!(
+                "The rectangle between {},{} and {},{} is too large to measure",
+                a.x, a.y, b.x, b.y
+            ))
+        })
+}
+
+/// Largest rectangle with two of the `corners` opposite each other for which `fits` holds.
+///
+/// `fits` is only called for rectangles that would be larger than the best one so far.
+fn largest_rectangle(
+    corners: &[Point],
+    fits: impl Fn(Point, Point) -> bool,
+) -> Option<(Point, Point)> {
+    let mut best = None;
+    let mut max_area = 0;
+    for (i, corner1) in corners.iter().enumerate() {
+        for corner2 in corners.iter().skip(i + 1) {
+            let area = rectangle_area(*corner1, *corner2);
+            if area > max_area && fits(*corner1, *corner2) {
+                max_area = area;
+                best = Some((*corner1, *corner2));
+            }
         }
     }
+    best
+}
 
-    fn compress_x(&self, x: usize) -> Option<usize> {
-        self.xs.binary_search(&x).ok()
-    }
+/// Area of the `best` rectangle, 0 if there is none.
+fn explained_area(ctx: &Context, best: Option<(Point, Point)>) -> usize {
+    let Some((a, b)) = best else {
+        ctx.explain(|| "No rectangle fits".to_string());
+        return 0;
+    };
+    let area = rectangle_area(a, b);
+    ctx.explain(|| {
+        format!(
+            "Largest rectangle between {},{} and {},{} with an area of {area}",
+            a.x, a.y, b.x, b.y
+        )
+    });
+    area
+}
 
-    fn compress_y(&self, y: usize) -> Option<usize> {
-        self.ys.binary_search(&y).ok()
-    }
+/// Draws the red tiles with the compression grid and the largest rectangles of both parts.
+fn to_svg(polygon: &Polygon, grid: &InsideGrid) -> Visualization {
+    let compressor = grid.compressor();
+    let (xs, ys) = (compressor.xs(), compressor.ys());
+    let min = (xs[0], ys[0]);
+    let max = (xs[xs.len() - 1], ys[ys.len() - 1]);
+    let mut canvas = SvgCanvas::new(min, max, SVG_WIDTH);
 
-    fn compress(&self, pos: &Position) -> Option<Position> {
-        Some(Position {
-            x: self.compress_x(pos.x)?,
-            y: self.compress_y(pos.y)?,
-        })
+    let grid_line = [("stroke", "lightgrey"), ("stroke-width", "0.5")];
+    for &x in xs {
+        canvas.line((x, min.1), (x, max.1), &grid_line);
     }
-
-    fn decompress_x(&self, x: usize) -> Option<usize> {
-        self.xs.get(x).copied()
+    for &y in ys {
+        canvas.line((min.0, y), (max.0, y), &grid_line);
     }
-
-    fn decompress_y(&self, y: usize) -> Option<usize> {
-        self.ys.get(y).copied()
+    canvas.polygon(
+        polygon.vertices().iter().map(|p| (p.x, p.y)),
+        &[("fill", "palegreen"), ("stroke", "red")],
+    );
+    if let Some((a, b)) = largest_rectangle(polygon.vertices(), |_, _| true) {
+        canvas.rect(
+            (a.x, a.y),
+            (b.x, b.y),
+            &[
+                ("fill", "none"),
+                ("stroke", "blue"),
+                ("stroke-dasharray", "4"),
+            ],
+        );
     }
-
-    fn decompress(&self, pos: &Position) -> Option<Position> {
-        Some(Position {
-            x: self.decompress_x(pos.x)?,
-            y: self.decompress_y(pos.y)?,
-        })
+    if let Some((a, b)) =
+        largest_rectangle(polygon.vertices(), |a, b| grid.contains_rectangle(a, b))
+    {
+        canvas.rect(
+            (a.x, a.y),
+            (b.x, b.y),
+            &[
+                ("fill", "orange"),
+                ("fill-opacity", "0.6"),
+                ("stroke", "darkorange"),
+            ],
+        );
     }
-}
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-struct Position {
-    x: usize,
-    y: usize,
+    canvas.finish()
 }
 
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
+    fn part1(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
         let corners = parse_input(input);
-        let mut max_area = 0;
-        for (i, corner1) in corners.iter().enumerate() {
-            for corner2 in corners.iter().skip(i + 1) {
-                let area =
-                    (corner1.x.abs_diff(corner2.x) + 1) * (corner1.y.abs_diff(corner2.y) + 1);
-                if area > max_area {
-                    max_area = area;
-                }
-            }
-        }
-        Some(max_area.abs_diff(0).into())
+        let best = largest_rectangle(&corners, |_, _| true);
+        Some(explained_area(ctx, best).into())
     }
 
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
-        let corners = parse_input(input);
-        let compressor = Compressor::new(&corners);
-        let compressed_corners: Vec<Position> = corners
-            .iter()
-            .map(|pos| compressor.compress(pos).unwrap())
-            .collect();
-        let mut grid = vec![vec![false; compressor.xs.len()]; compressor.ys.len()];
-        let mut last_corner = compressed_corners[0];
-        for &Position { x, y } in &compressed_corners {
-            grid[y][x] = true;
-            for row in grid
-                .iter_mut()
-                .take(last_corner.y.max(y) + 1)
-                .skip(last_corner.y.min(y))
-            {
-                for cell in row
-                    .iter_mut()
-                    .take(last_corner.x.max(x) + 1)
-                    .skip(last_corner.x.min(x))
-                {
-                    *cell = true;
-                }
-            }
-            last_corner = Position { x, y };
-        }
-        for row in grid
-            .iter_mut()
-            .take(last_corner.y.max(compressed_corners[0].y) + 1)
-            .skip(last_corner.y.min(compressed_corners[0].y))
-        {
-            for cell in row
-                .iter_mut()
-                .take(last_corner.x.max(compressed_corners[0].x) + 1)
-                .skip(last_corner.x.min(compressed_corners[0].x))
-            {
-                *cell = true;
-            }
-        }
+    fn part2(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let polygon = parse_polygon(input);
+        let grid = InsideGrid::new(&polygon);
+        let best = largest_rectangle(polygon.vertices(), |corner1, corner2| {
+            grid.contains_rectangle(corner1, corner2)
+        });
+        Some(explained_area(ctx, best).into())
+    }
+
+    fn visualize(&self, input: &str, _ctx: &Context) -> Option<Visualization> {
+        let polygon = parse_polygon(input);
+        let grid = InsideGrid::new(&polygon);
+        Some(to_svg(&polygon, &grid))
+    }
 
-        let flow_start = Position {
-            y: grid.len() / 4,
-            x: grid[0].len() / 2,
+    fn validate(&self, input: &str, _ctx: &Context) -> Vec<Violation> {
+        let corners = match parse_corners(input) {
+            Ok(corners) => corners,
+            Err(e) => return vec![Violation::malformed(input, &e)],
         };
-        grid[flow_start.y][flow_start.x] = true;
-        let mut flow_positions = vec![flow_start];
-        while let Some(Position { y: ry, x: rx }) = flow_positions.pop() {
-            let directions = [(-1isize, 0isize), (1, 0), (0, -1), (0, 1)];
-            for (dy, dx) in directions {
-                let new_y = ry.cast_signed() + dy;
-                let new_x = rx.cast_signed() + dx;
-                if new_y >= 0
-                    && new_y < grid.len().cast_signed()
-                    && new_x >= 0
-                    && new_x < grid[0].len().cast_signed()
-                {
-                    let (new_y, new_x) = (new_y.cast_unsigned(), new_x.cast_unsigned());
-                    if !grid[new_y][new_x] {
-                        grid[new_y][new_x] = true;
-                        flow_positions.push(Position { y: new_y, x: new_x });
-                    }
-                }
+        let Err(e) = Polygon::new(corners) else {
+            return vec![];
+        };
+        let assumption = match e {
+            PolygonError::TooFewVertices(_) => "There are at least 4 red tiles",
+            PolygonError::RepeatedVertex(_) => "Consecutive red tiles differ",
+            PolygonError::DiagonalEdge { .. } => "Consecutive red tiles share a row or column",
+            PolygonError::SelfIntersecting { .. } => {
+                "The outline of the red tiles doesn't cross itself"
             }
-        }
+        };
+        vec![Violation::new(assumption, e.to_string())]
+    }
 
-        let mut max_area = 0;
-        for (i, corner1) in compressed_corners.iter().enumerate() {
-            for corner2 in compressed_corners.iter().skip(i + 1) {
-                let (real_candidate1, real_candidate2) = (
-                    compressor.decompress(corner1).unwrap(),
-                    compressor.decompress(corner2).unwrap(),
-                );
-                let area = (real_candidate1.x.abs_diff(real_candidate2.x) + 1)
-                    * (real_candidate1.y.abs_diff(real_candidate2.y) + 1);
-                if grid
-                    .iter()
-                    .take(corner1.y.max(corner2.y) + 1)
-                    .skip(corner1.y.min(corner2.y))
-                    .all(|row| {
-                        row.iter()
-                            .take(corner1.x.max(corner2.x) + 1)
-                            .skip(corner1.x.min(corner2.x))
-                            .all(|&cell| cell)
-                    })
-                    && !compressed_corners.iter().any(|corner| {
-                        corner.x > corner1.x.min(corner2.x)
-                            && corner.x < corner1.x.max(corner2.x)
-                            && corner.y > corner1.y.min(corner2.y)
-                            && corner.y < corner1.y.max(corner2.y)
-                    })
-                    && area > max_area
-                {
-                    max_area = area;
+    /// A polygon with about `size` corners, made of columns whose top and bottom edges step up
+    /// and down independently.
+    ///
+    /// Bottoms stay below and tops above the middle height, so the outline never touches itself.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        const MIDDLE: usize = 50_000;
+        let columns = size.div_ceil(4).max(1);
+        let mut xs = vec![rng.range(0..=1000)];
+        for _ in 0..columns {
+            xs.push(xs[xs.len() - 1] + rng.range(1..=1000));
+        }
+        let mut heights = |range: RangeInclusive<usize>| {
+            let mut heights: Vec<usize> = vec![rng.range(range.clone())];
+            while heights.len() < columns {
+                let height = rng.range(range.clone());
+                if height != heights[heights.len() - 1] {
+                    heights.push(height);
                 }
             }
+            heights
+        };
+        let (bottoms, tops) = (heights(0..=MIDDLE - 1), heights(MIDDLE + 1..=2 * MIDDLE));
+        let mut corners = Vec::with_capacity(4 * columns);
+        for (i, &top) in tops.iter().enumerate() {
+            corners.extend([(xs[i], top), (xs[i + 1], top)]);
+        }
+        for (i, &bottom) in bottoms.iter().enumerate().rev() {
+            corners.extend([(xs[i + 1], bottom), (xs[i], bottom)]);
         }
-        Some(max_area.into())
+        let corners: Vec<String> = corners.iter().map(|(x, y)| format!("{x},{y}")).collect();
+        Some(corners.join("\n") + "\n")
     }
 }
 
@@ -195,29 +211,58 @@ impl Solution for Day {
 mod tests {
     use super::*;
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 9;
 
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(50.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(50.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(4_746_238_001.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(4_746_238_001.into()));
+    }
+
+    #[test]
+    fn test_validate() {
+        let ctx = Context::default();
+        assert_eq!(Day.validate("1,1\n5,1\n5,4\n1,4", &ctx), vec![]);
+        assert_eq!(
+            Day.validate("1,1\n5,1\n4,4\n1,4", &ctx),
+            vec![Violation::new(
+                "Consecutive red tiles share a row or column",
+                "Edge from 5,1 to 4,4 is not axis aligned"
+            )]
+        );
+        assert_eq!(
+            Day.validate("1,1\n5,1\n5,4", &ctx),
+            vec![Violation::new(
+                "There are at least 4 red tiles",
+                "A polygon needs at least 4 vertices, got 3"
+            )]
+        );
+        assert!(
+            Day.validate("1,1\n5,x", &ctx)[0]
+                .detail
+                .contains("line 2, column 3")
+        );
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(24.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(24.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(1_552_139_370.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(1_552_139_370.into()));
     }
 }
@@ -1,9 +1,12 @@
-use std::str::FromStr;
-
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use z3::{Optimize, ast::Int};
 
-use crate::solution::{Solution, SolvedValue};
+use crate::{
+    bytes,
+    generate::Rng,
+    parse::{self, ParseError},
+    solution::{Context, Implementation, Part, Solution, SolvedValue, Violation},
+};
 
 fn bool_vec_to_usize(vec: &[bool]) -> usize {
     vec.iter()
@@ -15,37 +18,61 @@ fn bool_vec_to_usize(vec: &[bool]) -> usize {
 type Button = Vec<usize>;
 
 #[derive(Debug)]
-struct Machine {
+pub(crate) struct Machine {
     lights: Vec<bool>,
     buttons: Vec<Button>,
     joltages: Vec<usize>,
 }
 
 impl Machine {
-    fn init_steps(&self) -> usize {
+    /// Fewest button presses to turn on the lights, `None` if the buttons can't do it.
+    fn init_steps(&self) -> Option<usize> {
         let mut queue = std::collections::VecDeque::new();
         queue.push_back((0, 0usize));
         let goal = bool_vec_to_usize(&self.lights);
         let mut visited = std::collections::HashSet::new();
 
         while let Some((current_state, steps)) = queue.pop_front() {
+            if goal == current_state {
+                return Some(steps);
+            }
             for button in &self.buttons {
                 let mut new_state = current_state;
                 for &idx in button {
                     new_state ^= 1 << idx;
                 }
-                if goal == current_state {
-                    return steps;
-                }
                 if visited.insert(new_state) {
                     queue.push_back((new_state, steps + 1));
                 }
             }
         }
-        usize::MAX
+        None
+    }
+
+    /// Same as [`Machine::init_steps`], but trying every set of buttons. Pressing a button twice
+    /// undoes it, so no button needs to be pressed more than once.
+    fn init_steps_brute_force(&self) -> Option<usize> {
+        let goal = bool_vec_to_usize(&self.lights);
+        let masks: Vec<usize> = self
+            .buttons
+            .iter()
+            .map(|button| button.iter().fold(0, |mask, &idx| mask | 1 << idx))
+            .collect();
+        (0..1usize << masks.len())
+            .filter(|&pressed| {
+                let state = masks
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| pressed >> i & 1 == 1)
+                    .fold(0, |state, (_, mask)| state ^ mask);
+                state == goal
+            })
+            .map(|pressed| pressed.count_ones() as usize)
+            .min()
     }
 
-    fn joltages(&self) -> usize {
+    /// Fewest button presses to reach the joltages, `None` if the buttons can't do it.
+    fn joltages(&self) -> Option<usize> {
         let optimizer = Optimize::new();
 
         let buttons: Vec<Int> = (0..self.buttons.len())
@@ -84,72 +111,240 @@ impl Machine {
             z3::SatResult::Sat => {
                 let model = optimizer.get_model().unwrap();
                 let value = model.eval(&button_sum, true).unwrap();
-                usize::try_from(value.as_u64().unwrap()).unwrap()
+                Some(usize::try_from(value.as_u64().unwrap()).unwrap())
             }
-            _ => usize::MAX,
+            _ => None,
         }
     }
 }
 
-impl FromStr for Machine {
-    type Err = ();
+/// The part of `fragment` of `text` between `open` and `close`.
+fn delimited<'a>(
+    text: &[u8],
+    fragment: &'a [u8],
+    open: u8,
+    close: u8,
+) -> Result<&'a [u8], ParseError> {
+    fragment
+        .strip_prefix(&[open])
+        .and_then(|inner| inner.strip_suffix(&[close]))
+        .ok_or_else(|| {
+            bytes::error_at(
+                text,
+                fragment,
+                format!("Expected {}...{}", char::from(open), char::from(close)),
+            )
+        })
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (lights, rest) = s.split_once(' ').ok_or(())?;
-        let lights = lights[1..lights.len() - 1]
-            .chars()
-            .map(|c| c == '#')
-            .collect();
-        let (buttons_str, joltages_str) = rest.split_once(" {").ok_or(())?;
-        let buttons = buttons_str
-            .split_ascii_whitespace()
-            .map(|btn_str| {
-                btn_str
-                    .trim_start_matches('(')
-                    .trim_end_matches(')')
-                    .split(',')
-                    .filter_map(|num_str| num_str.trim().parse().ok())
-                    .collect()
-            })
-            .collect();
-        let joltages = joltages_str
-            .trim_end_matches('}')
-            .split(',')
-            .filter_map(|num_str| num_str.trim().parse().ok())
-            .collect();
-        Ok(Machine {
-            lights,
-            buttons,
-            joltages,
+fn numbers(text: &[u8], list: &[u8]) -> Result<Vec<usize>, ParseError> {
+    bytes::split(list, b',')
+        .map(|number| bytes::number(text, number))
+        .collect()
+}
+
+/// Parses a machine like `[.##.] (3) (1,3) {3,5,4,7}` in `line` of `text`.
+fn parse_machine(text: &[u8], line: &[u8]) -> Result<Machine, ParseError> {
+    let mut parts = line
+        .split(u8::is_ascii_whitespace)
+        .filter(|part| !part.is_empty());
+    let lights = parts
+        .next()
+        .ok_or_else(|| bytes::error_at(text, &line[line.len()..], "Expected lights like [.##.]"))?;
+    let inner = delimited(text, lights, b'[', b']')?;
+    let lights = inner
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| match byte {
+            b'#' => Ok(true),
+            b'.' => Ok(false),
+            _ => {
+                let light = bytes::first_char(&inner[i..]);
+                Err(bytes::error_at(
+                    text,
+                    light,
+                    format!(
+                        "Expected light # or ., found {:?}",
+                        String::from_utf8_lossy(light)
+                    ),
+                ))
+            }
         })
+        .collect::<Result<_, _>>()?;
+    let mut buttons = Vec::new();
+    let mut joltages = None;
+    for part in parts {
+        if joltages.is_some() {
+            return Err(bytes::error_at(
+                text,
+                part,
+                "Expected nothing after the joltages",
+            ));
+        }
+        if part.starts_with(b"{") {
+            joltages = Some(numbers(text, delimited(text, part, b'{', b'}')?)?);
+        } else {
+            buttons.push(numbers(text, delimited(text, part, b'(', b')')?)?);
+        }
+    }
+    Ok(Machine {
+        lights,
+        buttons,
+        joltages: joltages.ok_or_else(|| {
+            bytes::error_at(
+                text,
+                &line[line.len()..],
+                "Expected joltages like {3,5,4,7}",
+            )
+        })?,
+    })
+}
+
+pub(crate) fn parse_machines(input: &str) -> Result<Vec<Machine>, ParseError> {
+    let text = input.as_bytes();
+    bytes::parse_lines(text, |line| parse_machine(text, line))
+}
+
+/// The assumptions of the solvers that `machines` break: lights and joltages have to fit into
+/// the bit sets and 32 bit integers they are solved with.
+fn violations(machines: &[Machine]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (i, machine) in machines.iter().enumerate() {
+        if machine.lights.len() > usize::BITS as usize {
+            violations.push(Violation::new(
+                "Machines have at most 64 lights",
+                format!("Machine {} has {} lights", i + 1, machine.lights.len()),
+            ));
+        }
+        if let Some(light) = machine
+            .buttons
+            .iter()
+            .flatten()
+            .find(|&&light| light >= machine.lights.len())
+        {
+            violations.push(Violation::new(
+                "Buttons only toggle lights of their machine",
+                format!("Machine {} has no light {light}", i + 1),
+            ));
+        }
+        if let Some(joltage) = machine
+            .joltages
+            .iter()
+            .find(|&&joltage| u32::try_from(joltage).is_err())
+        {
+            violations.push(Violation::new(
+                "Joltages fit into 32 bits",
+                format!("Machine {} needs a joltage of {joltage}", i + 1),
+            ));
+        }
     }
+    violations
+}
+
+fn parse_input(input: &str) -> Vec<Machine> {
+    let machines = parse::or_report(parse_machines(input), input);
+    if let Some(violation) = violations(&machines).first() {
+        parse::reject(violation);
+    }
+    machines
+}
+
+/// The presses of every machine, giving up on the input if a machine can't be solved.
+fn all_solved(presses: &[Option<usize>]) -> Vec<usize> {
+    presses
+        .iter()
+        .enumerate()
+        .map(|(i, presses)| {
+            presses.unwrap_or_else(|| parse::reject(format!("Machine {} can't be solved", i + 1)))
+        })
+        .collect()
+}
+
+fn explain_optimum(ctx: &Context, presses: &[usize]) {
+    for (i, presses) in presses.iter().enumerate() {
+        ctx.explain(|| format!("Machine {}: {presses} button presses", i + 1));
+    }
+}
+
+/// Part 1 trying every set of buttons of every machine.
+fn brute_force_part1(input: &str) -> SolvedValue {
+    let machines = parse_input(input);
+    let steps: Vec<Option<usize>> = machines
+        .par_iter()
+        .map(Machine::init_steps_brute_force)
+        .collect();
+    all_solved(&steps).iter().sum::<usize>().into()
 }
 
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
-        let machines = input
-            .lines()
-            .map(str::parse)
-            .collect::<Result<Vec<Machine>, _>>()
-            .ok()?;
-        Some(
-            machines
-                .par_iter()
-                .map(Machine::init_steps)
-                .sum::<usize>()
-                .into(),
-        )
-    }
-
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
-        let machines = input
-            .lines()
-            .map(str::parse)
-            .collect::<Result<Vec<Machine>, _>>()
-            .ok()?;
-        Some(machines.iter().map(Machine::joltages).sum::<usize>().into())
+    fn part1(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let machines = parse_input(input);
+        let steps: Vec<Option<usize>> = machines.par_iter().map(Machine::init_steps).collect();
+        let steps = all_solved(&steps);
+        explain_optimum(ctx, &steps);
+        Some(steps.iter().sum::<usize>().into())
+    }
+
+    fn part2(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let machines = parse_input(input);
+        let presses: Vec<Option<usize>> = machines.iter().map(Machine::joltages).collect();
+        let presses = all_solved(&presses);
+        explain_optimum(ctx, &presses);
+        Some(presses.iter().sum::<usize>().into())
+    }
+
+    fn validate(&self, input: &str, _ctx: &Context) -> Vec<Violation> {
+        let machines = match parse_machines(input) {
+            Ok(machines) => machines,
+            Err(e) => return vec![Violation::malformed(input, &e)],
+        };
+        violations(&machines)
+    }
+
+    fn implementations(&self) -> &'static [Implementation] {
+        &[Implementation {
+            name: "brute_force",
+            part: Part::One,
+            solve: |input, _ctx| Some(brute_force_part1(input)),
+        }]
+    }
+
+    /// `size` machines whose lights and joltages can always be reached by pressing the buttons.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        let machines: Vec<String> = (0..size)
+            .map(|_| {
+                let lights = rng.range(3..=10);
+                let mut indicators = vec![false; lights];
+                let mut joltages = vec![0; lights];
+                let mut buttons = Vec::new();
+                for _ in 0..rng.range(2..=lights + 3) {
+                    let mut button: Vec<usize> = (0..lights).collect();
+                    rng.shuffle(&mut button);
+                    button.truncate(rng.range(1..=lights.min(6)));
+                    button.sort_unstable();
+                    let presses = rng.range(0..=30);
+                    for &light in &button {
+                        indicators[light] ^= presses % 2 == 1;
+                        joltages[light] += presses;
+                    }
+                    let button: Vec<String> = button.iter().map(ToString::to_string).collect();
+                    buttons.push(format!("({})", button.join(",")));
+                }
+                let indicators: String = indicators
+                    .iter()
+                    .map(|&on| if on { '#' } else { '.' })
+                    .collect();
+                let joltages: Vec<String> = joltages.iter().map(ToString::to_string).collect();
+                format!(
+                    "[{indicators}] {} {{{}}}\n",
+                    buttons.join(" "),
+                    joltages.join(",")
+                )
+            })
+            .collect();
+        Some(machines.concat())
     }
 }
 
@@ -157,29 +352,41 @@ impl Solution for Day {
 mod tests {
     use super::*;
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 10;
 
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_machines("[.#] (0) {1}\n[.ä#] (1) {1}\n").err(),
+            Some(ParseError::new(15, 2, "Expected light # or ., found \"ä\""))
+        );
+    }
+
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(7.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(7.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(438.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(438.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(33.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(33.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(16_463.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(16_463.into()));
     }
 }
@@ -1,88 +1,613 @@
-use std::{collections::HashMap, sync::RwLock};
+use std::{collections::HashSet, fmt::Display};
 
-use crate::solution::{Solution, SolvedValue};
+use crate::{
+    generate::Rng,
+    graph::{Cycle, Graph, NodeId},
+    observe::{Event, Level},
+    parse::{self, ParseError},
+    solution::{Context, Parameter, Solution, SolvedValue, Violation, parses_as},
+    visualize::{DotGraph, Visualization},
+};
 
-fn parse_input(input: &str) -> HashMap<&str, Vec<&str>> {
-    input
-        .lines()
-        .map(|line| {
-            let (input, output) = line.split_once(": ").unwrap();
-            let outputs = output.split_whitespace().collect();
-            (input, outputs)
+#[derive(Debug, PartialEq, Eq)]
+enum PathError {
+    UnknownDevice(String),
+    Cycle(Vec<String>),
+    Overflow,
+    TooManyWaypoints(usize),
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathError::UnknownDevice(name) => write!(f, "Unknown device {name}"),
+            PathError::Cycle(names) => write!(f, "Devices {} form a cycle", names.join(", ")),
+            PathError::Overflow => write!(f, "Number of paths doesn't fit into 128 bits"),
+            PathError::TooManyWaypoints(count) => {
+                write!(
+                    f,
+                    "{count} waypoints are too many to track which were passed"
+                )
+            }
+        }
+    }
+}
+
+fn device_id(devices: &Graph<&str>, name: &str) -> Result<NodeId, PathError> {
+    devices
+        .id(name)
+        .ok_or_else(|| PathError::UnknownDevice(name.to_string()))
+}
+
+/// Orders the `relevant` devices topologically.
+fn order_devices(devices: &Graph<&str>, relevant: &[bool]) -> Result<Vec<NodeId>, PathError> {
+    let order = devices
+        .retain_nodes(relevant)
+        .topological_order()
+        .map_err(|Cycle(cycle)| {
+            PathError::Cycle(
+                cycle
+                    .iter()
+                    .map(|&id| devices.key(id).to_string())
+                    .collect(),
+            )
+        })?;
+    Ok(order
+        .into_iter()
+        .filter(|&device| relevant[device])
+        .collect())
+}
+
+/// Marks the devices on some path from `start` to `goal` and orders them topologically.
+fn devices_between(
+    devices: &Graph<&str>,
+    start: NodeId,
+    goal: NodeId,
+) -> Result<(Vec<bool>, Vec<NodeId>), PathError> {
+    let relevant = devices.nodes_between(start, goal);
+    let order = order_devices(devices, &relevant)?;
+    Ok((relevant, order))
+}
+
+/// Number of paths from every device to `goal`, going through the `relevant` devices in `order`.
+fn paths_to_goal(
+    devices: &Graph<&str>,
+    relevant: &[bool],
+    order: &[NodeId],
+    goal: NodeId,
+) -> Result<Vec<u128>, PathError> {
+    let mut to_goal = vec![0u128; devices.len()];
+    for &device in order.iter().rev() {
+        to_goal[device] = if device == goal {
+            1
+        } else {
+            devices
+                .successors(device)
+                .iter()
+                .filter(|&&next| relevant[next])
+                .try_fold(0u128, |sum, &next| sum.checked_add(to_goal[next]))
+                .ok_or(PathError::Overflow)?
+        };
+    }
+    Ok(to_goal)
+}
+
+/// Counts the paths from `start` to `goal` that pass all `waypoints` in any order.
+///
+/// Only devices that lie on some path from `start` to `goal` are considered. They are visited in
+/// topological order while counting paths per set of waypoints seen so far, so every path is
+/// counted exactly once no matter in which order it passes the waypoints. Fails instead of
+/// looping forever if these devices contain a cycle, as there would be infinitely many paths.
+/// Waypoints given more than once only count once.
+fn count_paths(
+    devices: &Graph<&str>,
+    start: &str,
+    goal: &str,
+    waypoints: &[&str],
+) -> Result<u128, PathError> {
+    let (start, goal) = (device_id(devices, start)?, device_id(devices, goal)?);
+    let mut waypoints = waypoints
+        .iter()
+        .map(|waypoint| device_id(devices, waypoint))
+        .collect::<Result<Vec<_>, _>>()?;
+    waypoints.sort_unstable();
+    waypoints.dedup();
+    if waypoints.len() >= usize::BITS as usize {
+        return Err(PathError::TooManyWaypoints(waypoints.len()));
+    }
+    let (relevant, order) = devices_between(devices, start, goal)?;
+    if waypoints.iter().any(|&waypoint| !relevant[waypoint]) {
+        return Ok(0);
+    }
+
+    let full_mask = (1 << waypoints.len()) - 1;
+    let waypoint_mask = |device| {
+        waypoints
+            .iter()
+            .position(|&waypoint| waypoint == device)
+            .map_or(0, |i| 1 << i)
+    };
+    let mut paths = vec![vec![0u128; full_mask + 1]; devices.len()];
+    paths[start][waypoint_mask(start)] = 1;
+    for device in order {
+        if device == goal {
+            break;
+        }
+        let counts = std::mem::take(&mut paths[device]);
+        for &next in devices.successors(device) {
+            if relevant[next] {
+                let next_mask = waypoint_mask(next);
+                for (mask, count) in counts.iter().enumerate() {
+                    let target = &mut paths[next][mask | next_mask];
+                    *target = target.checked_add(*count).ok_or(PathError::Overflow)?;
+                }
+            }
+        }
+    }
+    Ok(paths[goal][full_mask])
+}
+
+/// Number of paths from every device to a goal, which allows to list the paths from a start
+/// device one by one or to jump right to the n-th of them.
+struct PathIndex<'a> {
+    devices: &'a Graph<&'a str>,
+    start: NodeId,
+    goal: NodeId,
+    to_goal: Vec<u128>,
+}
+
+impl<'a> PathIndex<'a> {
+    fn new(devices: &'a Graph<&'a str>, start: &str, goal: &str) -> Result<Self, PathError> {
+        let (start, goal) = (device_id(devices, start)?, device_id(devices, goal)?);
+        let (relevant, order) = devices_between(devices, start, goal)?;
+        let to_goal = paths_to_goal(devices, &relevant, &order, goal)?;
+        Ok(PathIndex {
+            devices,
+            start,
+            goal,
+            to_goal,
         })
-        .collect()
+    }
+
+    fn count(&self) -> u128 {
+        self.to_goal[self.start]
+    }
+
+    /// Successors of `device` that still lead to the goal, in the order paths are listed.
+    fn continuations(&self, device: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.devices
+            .successors(device)
+            .iter()
+            .copied()
+            .filter(|&next| self.to_goal[next] > 0)
+    }
+
+    fn names(&self, path: impl Iterator<Item = NodeId>) -> Vec<&'a str> {
+        path.map(|device| *self.devices.key(device)).collect()
+    }
+
+    /// Lists all paths lazily with a depth first search that only follows devices leading to the goal.
+    fn paths(&self) -> Paths<'_, 'a> {
+        let stack = if self.count() > 0 {
+            vec![(self.start, 0)]
+        } else {
+            vec![]
+        };
+        Paths { index: self, stack }
+    }
+
+    /// Finds the path `paths()` would yield at position `n` without listing the ones before.
+    ///
+    /// At every device the paths are grouped by the next device, so whole groups can be skipped using
+    /// their count.
+    fn nth(&self, mut n: u128) -> Option<Vec<&'a str>> {
+        if n >= self.count() {
+            return None;
+        }
+        let mut path = vec![self.start];
+        let mut device = self.start;
+        while device != self.goal {
+            for next in self.continuations(device) {
+                if n < self.to_goal[next] {
+                    device = next;
+                    break;
+                }
+                n -= self.to_goal[next];
+            }
+            path.push(device);
+        }
+        Some(self.names(path.into_iter()))
+    }
+}
+
+struct Paths<'i, 'a> {
+    index: &'i PathIndex<'a>,
+    /// Devices of the current path with the number of continuations already explored
+    stack: Vec<(NodeId, usize)>,
 }
 
-fn get_paths_count_to_out<'a>(
-    start: &'a str,
-    goal: &'a str,
-    machines: &'a HashMap<&'a str, Vec<&'a str>>,
-    memo: &RwLock<HashMap<&'a str, usize>>,
-) -> usize {
-    if start == goal {
-        return 1;
+impl<'a> Iterator for Paths<'_, 'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((device, explored)) = self.stack.last_mut() {
+            let device = *device;
+            if device == self.index.goal {
+                let path = self
+                    .index
+                    .names(self.stack.iter().map(|(device, _)| *device));
+                self.stack.pop();
+                return Some(path);
+            }
+            let next = self.index.continuations(device).nth(*explored);
+            *explored += 1;
+            match next {
+                Some(next) => self.stack.push((next, 0)),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        None
     }
-    if let Some(count) = memo.read().unwrap().get(start) {
-        return *count;
+}
+
+/// Devices that are highlighted in the visualisation, as they are start, goal or waypoint of a part.
+const HIGHLIGHTED: [&str; 5] = ["svr", "fft", "dac", "you", "out"];
+
+/// Renders all devices labelled with their number of paths to `goal`.
+///
+/// Connections into devices without any path to `goal` don't contribute to any count and are greyed out.
+fn to_dot(devices: &Graph<&str>, goal: &str) -> Result<Visualization, PathError> {
+    let goal = device_id(devices, goal)?;
+    let relevant = devices.reaching(goal);
+    let order = order_devices(devices, &relevant)?;
+    let to_goal = paths_to_goal(devices, &relevant, &order, goal)?;
+
+    let mut dot = DotGraph::new("devices")
+        .graph_attributes(&[("rankdir", "LR")])
+        .node_defaults(&[("shape", "box")]);
+    for device in 0..devices.len() {
+        let name = *devices.key(device);
+        let label = format!("{name}\n{}", to_goal[device]);
+        if HIGHLIGHTED.contains(&name) {
+            dot.node(
+                name,
+                &[
+                    ("label", &label),
+                    ("style", "filled"),
+                    ("fillcolor", "gold"),
+                ],
+            );
+        } else if to_goal[device] == 0 {
+            dot.node(
+                name,
+                &[("label", &label), ("color", "grey"), ("fontcolor", "grey")],
+            );
+        } else {
+            dot.node(name, &[("label", &label)]);
+        }
+        for &next in devices.successors(device) {
+            if to_goal[next] == 0 {
+                dot.edge(name, devices.key(next), &[("color", "grey")]);
+            } else {
+                dot.edge(name, devices.key(next), &[]);
+            }
+        }
     }
-    let mut total_paths = 0;
-    if let Some(neighbors) = machines.get(start) {
-        for &neighbor in neighbors {
-            total_paths += get_paths_count_to_out(neighbor, goal, machines, memo);
+    Ok(dot.finish())
+}
+
+fn parse_devices(input: &str) -> Result<Graph<&str>, ParseError> {
+    let mut devices = Graph::new();
+    for line in input.lines().filter(|line| !line.trim().is_empty()) {
+        let (device, outputs) = parse::split_once(input, line, ": ")?;
+        let device = devices.intern(device);
+        for output in outputs.split_whitespace() {
+            let output = devices.intern(output);
+            devices.add_edge(device, output);
         }
     }
-    memo.write().unwrap().insert(start, total_paths);
-    total_paths
+    Ok(devices)
+}
+
+fn parse_input(input: &str) -> Graph<&str> {
+    parse::or_report(parse_devices(input), input)
 }
 
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
-        let machines = parse_input(input);
-        let res = get_paths_count_to_out("you", "out", &machines, &RwLock::new(HashMap::new()));
-        Some(res.into())
+    fn part1(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
+        let devices = parse_input(input);
+        if ctx.is_observed() {
+            for device in 0..devices.len() {
+                for &next in devices.successors(device) {
+                    ctx.emit(|| Event::Edge {
+                        from: devices.key(device).to_string(),
+                        to: devices.key(next).to_string(),
+                    });
+                }
+            }
+        }
+        let paths = PathIndex::new(&devices, "you", "out").unwrap_or_else(|e| parse::reject(e));
+        if ctx.logs(Level::Explain) {
+            let shown: usize = ctx.param("explain_paths");
+            for (i, path) in paths.paths().take(shown).enumerate() {
+                ctx.explain(|| format!("Path {}: {}", i + 1, path.join(" -> ")));
+            }
+            // The last path, unless it was among the ones above
+            if paths.count() > shown as u128
+                && let Some(last) = paths.nth(paths.count() - 1)
+            {
+                ctx.explain(|| format!("Path {}: {}", paths.count(), last.join(" -> ")));
+            }
+        }
+        Some(SolvedValue::wide(paths.count()))
+    }
+
+    fn part2(&self, input: &str, _ctx: &Context) -> Option<SolvedValue> {
+        let devices = parse_input(input);
+        let res = count_paths(&devices, "svr", "out", &["fft", "dac"])
+            .unwrap_or_else(|e| parse::reject(e));
+        Some(SolvedValue::wide(res))
+    }
+
+    fn visualize(&self, input: &str, _ctx: &Context) -> Option<Visualization> {
+        let devices = parse_input(input);
+        Some(to_dot(&devices, "out").unwrap_or_else(|e| parse::reject(e)))
+    }
+
+    fn validate(&self, input: &str, _ctx: &Context) -> Vec<Violation> {
+        let devices = match parse_devices(input) {
+            Ok(devices) => devices,
+            Err(e) => return vec![Violation::malformed(input, &e)],
+        };
+        let mut violations = Vec::new();
+        if let Err(Cycle(cycle)) = devices.topological_order() {
+            let names: Vec<&str> = cycle.iter().map(|&id| *devices.key(id)).collect();
+            violations.push(Violation::new(
+                "The devices form no cycle",
+                format!("{} form a cycle", names.join(" -> ")),
+            ));
+        }
+        if let (Some(fft), Some(dac)) = (devices.id("fft"), devices.id("dac"))
+            && devices.reachable_from(dac)[fft]
+        {
+            violations.push(Violation::new(
+                "fft comes before dac",
+                "There is a path from dac to fft",
+            ));
+        }
+        violations
     }
 
-    fn part2(&self, input: &str) -> Option<SolvedValue> {
-        let machines = parse_input(input);
-        // We know that the order has to be either svr -> fft -> dac -> out or svr -> dac -> fft -> out,
-        // because if fft -> dac -> fft or dac -> fft -> dac happens, there would be cycles and infinite paths.
-        // By trying it, we find that only the first one yields paths.
-        let svr_fft = get_paths_count_to_out("svr", "fft", &machines, &RwLock::new(HashMap::new()));
-        let fft_dac = get_paths_count_to_out("fft", "dac", &machines, &RwLock::new(HashMap::new()));
-        let dac_out = get_paths_count_to_out("dac", "out", &machines, &RwLock::new(HashMap::new()));
-        Some((svr_fft * fft_dac * dac_out).into())
+    fn parameters(&self) -> &'static [Parameter] {
+        &[Parameter {
+            name: "explain_paths",
+            default: "10",
+            description: "Number of paths to list in explain mode",
+            check: parses_as::<usize>,
+        }]
+    }
+
+    /// A chain of `size` segments from `svr` to `out` through `fft` and `dac`.
+    ///
+    /// Each segment fans out from one device to a few parallel ones and joins again, some of them
+    /// also feeding dead ends. `you` is one of the parallel devices of the first segment. Fan outs
+    /// stop once the number of paths reaches about 2^50, so counting them never overflows.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        const RESERVED: [&str; 5] = ["svr", "you", "fft", "dac", "out"];
+        let segments = size.max(3);
+        let mut used: HashSet<String> = RESERVED.iter().map(ToString::to_string).collect();
+        let mut name = |rng: &mut Rng| loop {
+            let name: String = (0..3)
+                .map(|_| char::from(b'a' + u8::try_from(rng.range(0..=25)).expect("Letter fits")))
+                .collect();
+            if used.insert(name.clone()) {
+                return name;
+            }
+        };
+        let mut joints: Vec<String> = (0..=segments).map(|_| name(rng)).collect();
+        joints[0] = "svr".to_string();
+        joints[segments / 3] = "fft".to_string();
+        joints[2 * segments / 3] = "dac".to_string();
+        joints[segments] = "out".to_string();
+
+        let mut lines = Vec::new();
+        let mut paths: u128 = 1;
+        for segment in 0..segments {
+            let width = if paths < 1 << 50 { rng.range(1..=3) } else { 1 };
+            paths *= width as u128;
+            let devices: Vec<String> = (0..width)
+                .map(|i| {
+                    if segment == 0 && i == 0 {
+                        "you".to_string()
+                    } else {
+                        name(rng)
+                    }
+                })
+                .collect();
+            lines.push(format!("{}: {}", joints[segment], devices.join(" ")));
+            for device in devices {
+                let mut outputs = vec![joints[segment + 1].clone()];
+                if rng.chance(1, 4) {
+                    outputs.push(name(rng));
+                }
+                rng.shuffle(&mut outputs);
+                lines.push(format!("{device}: {}", outputs.join(" ")));
+            }
+        }
+        rng.shuffle(&mut lines);
+        Some(lines.join("\n") + "\n")
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
     use super::*;
+    use crate::observe::Observer;
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 11;
 
+    #[test]
+    fn test_waypoints_in_any_order() {
+        let devices = parse_input("a: b c\nb: c d\nc: d\nd: e");
+        assert_eq!(count_paths(&devices, "a", "e", &[]), Ok(3));
+        assert_eq!(count_paths(&devices, "a", "e", &["d", "b"]), Ok(2));
+        assert_eq!(count_paths(&devices, "a", "e", &["c", "b"]), Ok(1));
+        assert_eq!(count_paths(&devices, "b", "e", &["a"]), Ok(0));
+        assert_eq!(count_paths(&devices, "a", "e", &["d", "b", "d"]), Ok(2));
+        assert_eq!(
+            count_paths(&devices, "a", "x", &[]),
+            Err(PathError::UnknownDevice("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_too_many_waypoints() {
+        let input = (0..64)
+            .map(|i| format!("{i}: {}", i + 1))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let devices = parse_input(&input);
+        let waypoints: Vec<String> = (1..=64).map(|i| i.to_string()).collect();
+        let waypoints: Vec<&str> = waypoints.iter().map(String::as_str).collect();
+        assert_eq!(
+            count_paths(&devices, "0", "64", &waypoints),
+            Err(PathError::TooManyWaypoints(64))
+        );
+    }
+
+    #[test]
+    fn test_cycle_is_an_error() {
+        let devices = parse_input("a: b x\nb: c\nc: b d\nx: y");
+        assert!(matches!(
+            count_paths(&devices, "a", "d", &[]),
+            Err(PathError::Cycle(_))
+        ));
+        // The cycle is not on any path to y
+        assert_eq!(count_paths(&devices, "a", "y", &[]), Ok(1));
+    }
+
+    #[test]
+    fn test_list_and_pick_paths() {
+        let devices = parse_input("a: b c x\nb: c d\nc: d\nd: e\nx: y");
+        let index = PathIndex::new(&devices, "a", "e").unwrap();
+        let paths: Vec<Vec<&str>> = index.paths().collect();
+        assert_eq!(
+            paths,
+            vec![
+                vec!["a", "b", "c", "d", "e"],
+                vec!["a", "b", "d", "e"],
+                vec!["a", "c", "d", "e"]
+            ]
+        );
+        for (n, path) in paths.iter().enumerate() {
+            assert_eq!(index.nth(n as u128).as_ref(), Some(path));
+        }
+        assert_eq!(index.nth(3), None);
+        assert_eq!(
+            PathIndex::new(&devices, "e", "a").unwrap().paths().count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_dot_export() {
+        let devices = parse_input("you: a b\na: out\nb: c");
+        let dot = to_dot(&devices, "out").unwrap().to_string();
+        assert!(dot.contains(r#""you" [label="you\n1", style="filled", fillcolor="gold"];"#));
+        assert!(dot.contains(r#""a" [label="a\n1"];"#));
+        assert!(dot.contains(r#""b" [label="b\n0", color="grey", fontcolor="grey"];"#));
+        assert!(dot.contains(r#""you" -> "a";"#));
+        assert!(dot.contains(r#""you" -> "b" [color="grey"];"#));
+    }
+
+    #[test]
+    fn test_validate() {
+        let ctx = Context::default();
+        assert_eq!(Day.validate("svr: fft\nfft: dac\ndac: out", &ctx), vec![]);
+        let violations = Day.validate("svr: dac\ndac: fft\nfft: a\na: fft out", &ctx);
+        assert_eq!(
+            violations,
+            vec![
+                Violation::new("The devices form no cycle", "a -> fft form a cycle"),
+                Violation::new("fft comes before dac", "There is a path from dac to fft"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explained_paths() {
+        #[derive(Debug, Default)]
+        struct Explained(Mutex<Vec<String>>);
+
+        impl Observer for Explained {
+            fn observe(&self, event: Event) {
+                if let Event::Log { message, .. } = event {
+                    self.0.lock().unwrap().push(message);
+                }
+            }
+        }
+
+        let input = read_input(DAY, true, 1).unwrap();
+        for (limit, expected) in [
+            ("2", vec![1, 2, 5]),
+            ("5", vec![1, 2, 3, 4, 5]),
+            ("10", vec![1, 2, 3, 4, 5]),
+        ] {
+            let explained = Arc::new(Explained::default());
+            let mut ctx = read_context(&Day, DAY, true, 1).unwrap();
+            ctx.set_param("explain_paths", limit).unwrap();
+            ctx.set_explain(true);
+            ctx.set_observer(Some(explained.clone()));
+            Day.part1(&input, &ctx);
+            let numbers: Vec<usize> = explained
+                .0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|message| {
+                    message
+                        .strip_prefix("Path ")?
+                        .split_once(':')?
+                        .0
+                        .parse()
+                        .ok()
+                })
+                .collect();
+            assert_eq!(numbers, expected, "explain_paths = {limit}");
+        }
+    }
+
     #[test]
     fn test_part1_example() {
         let input = read_input(DAY, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(5.into()));
+        let ctx = read_context(&Day, DAY, true, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(5.into()));
     }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(555.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(555.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(2.into()));
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(2.into()));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(502_447_498_690_860.into()));
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), Some(502_447_498_690_860.into()));
     }
 }
@@ -1,70 +1,134 @@
-use crate::solution::{Solution, SolvedValue};
+use std::fmt::Write;
 
-fn parse_input(input: &str) -> Vec<((usize, usize), Vec<usize>)> {
-    input
-        .split("\n\n")
-        .last()
-        .unwrap()
+use crate::{
+    generate::Rng,
+    parse::{self, ParseError},
+    solution::{Context, Solution, SolvedValue},
+};
+
+type Region = ((usize, usize), Vec<usize>);
+
+fn parse_regions(input: &str) -> Result<Vec<Region>, ParseError> {
+    let regions = input.split("\n\n").last().unwrap_or(input);
+    regions
         .lines()
+        .filter(|line| !line.trim().is_empty())
         .map(|line| {
-            let (size, counts) = line.split_once(": ").unwrap_or(("0x0", "0 0 0 0 0 0"));
-            let (width, height) = size.split_once('x').unwrap();
-            let size = (width.parse().unwrap(), height.parse().unwrap());
-            let index_counts: Vec<usize> = counts
+            let (size, counts) = parse::split_once(input, line, ": ")?;
+            let (width, height) = parse::split_once(input, size, "x")?;
+            let size = (parse::number(input, width)?, parse::number(input, height)?);
+            let index_counts = counts
                 .split_whitespace()
-                .map(|count| count.parse().unwrap())
-                .collect();
-            (size, index_counts)
+                .map(|count| parse::number(input, count))
+                .collect::<Result<_, _>>()?;
+            Ok((size, index_counts))
         })
         .collect()
 }
 
+fn parse_input(input: &str) -> Vec<Region> {
+    parse::or_report(parse_regions(input), input)
+}
+
 pub struct Day;
 
 impl Solution for Day {
-    fn part1(&self, input: &str) -> Option<SolvedValue> {
+    fn part1(&self, input: &str, ctx: &Context) -> Option<SolvedValue> {
         let items = parse_input(input);
         let possibles = items
             .iter()
-            .filter(|((width, height), counts)| {
-                (width * height) >= counts.iter().sum::<usize>() * 9
+            .enumerate()
+            .filter(|(i, ((width, height), counts))| {
+                let area = (*width as u128) * (*height as u128);
+                let needed = counts.iter().map(|&count| count as u128).sum::<u128>() * 9;
+                ctx.explain(|| {
+                    format!(
+                        "Region {} ({width}x{height}): {area} cells, {needed} needed in 3x3 boxes, {}",
+                        i + 1,
+                        if area >= needed { "fits" } else { "too small" }
+                    )
+                });
+                area >= needed
             })
             .count();
         Some(possibles.into())
     }
 
-    fn part2(&self, _input: &str) -> Option<SolvedValue> {
+    fn part2(&self, _input: &str, _ctx: &Context) -> Option<SolvedValue> {
         None
     }
+
+    /// Six presents of 7 cells each and `size` regions.
+    ///
+    /// Every region either has room for all its presents side by side in 3x3 boxes or fewer cells
+    /// than its presents, so the area check gives the right answer.
+    fn generate(&self, size: usize, rng: &mut Rng) -> Option<String> {
+        const SHAPES: usize = 6;
+        let mut input = String::new();
+        for shape in 0..SHAPES {
+            let mut cells = ['#'; 9];
+            let mut holes: Vec<usize> = (0..9).collect();
+            rng.shuffle(&mut holes);
+            for &hole in &holes[..2] {
+                cells[hole] = '.';
+            }
+            let _ = writeln!(input, "{shape}:");
+            for row in cells.chunks(3) {
+                input.extend(row);
+                input.push('\n');
+            }
+            input.push('\n');
+        }
+        for _ in 0..size {
+            let (width, height) = (rng.range(35..=50), rng.range(35..=50));
+            let presents = if rng.chance(1, 2) {
+                let boxes = (width / 3) * (height / 3);
+                rng.range(boxes / 2..=boxes)
+            } else {
+                width * height / 7 + rng.range(1..=20)
+            };
+            let mut counts = [0; SHAPES];
+            for _ in 0..presents {
+                counts[rng.range(0..=SHAPES - 1)] += 1;
+            }
+            let counts: Vec<String> = counts.iter().map(ToString::to_string).collect();
+            let _ = writeln!(input, "{width}x{height}: {}", counts.join(" "));
+        }
+        Some(input)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::solution::Solution;
-    use crate::utils::read_input;
+    use crate::utils::{read_context, read_input};
 
     const DAY: usize = 12;
 
     // #[test]
     // fn test_part1_example() {
     //     let input = read_input(DAY, true, 1).unwrap();
-    //     assert_eq!(Day.part1(&input), None);
+    //     let ctx = read_context(&Day, DAY, true, 1).unwrap();
+    //     assert_eq!(Day.part1(&input, &ctx), None);
     // }
     #[test]
     fn test_part1_challenge() {
         let input = read_input(DAY, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), Some(519.into()));
+        let ctx = read_context(&Day, DAY, false, 1).unwrap();
+        assert_eq!(Day.part1(&input, &ctx), Some(519.into()));
     }
 
     #[test]
     fn test_part2_example() {
         let input = read_input(DAY, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), None);
+        let ctx = read_context(&Day, DAY, true, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), None);
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(DAY, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), None);
+        let ctx = read_context(&Day, DAY, false, 2).unwrap();
+        assert_eq!(Day.part2(&input, &ctx), None);
     }
 }
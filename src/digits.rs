@@ -0,0 +1,202 @@
+use std::ops::RangeInclusive;
+
+/// Number of decimal digits of `number` (`0` has one digit).
+#[must_use]
+pub fn digit_count(number: usize) -> u32 {
+    number.checked_ilog10().unwrap_or(0) + 1
+}
+
+/// Möbius function for the small numbers that occur as repetition counts.
+fn moebius(mut n: u32) -> i8 {
+    let mut result = 1;
+    let mut factor = 2;
+    while factor * factor <= n {
+        if n.is_multiple_of(factor) {
+            n /= factor;
+            if n.is_multiple_of(factor) {
+                return 0;
+            }
+            result = -result;
+        }
+        factor += 1;
+    }
+    if n > 1 { -result } else { result }
+}
+
+/// The blocks of `length / repetitions` digits that, repeated `repetitions` times, give a number
+/// with exactly `length` digits in `range`, and the factor turning a block into that number.
+///
+/// Such a number is `block * (1 + 10^m + 10^2m + ...)` with `m` being the block length, so the valid
+/// blocks form a contiguous range that can be computed directly.
+fn blocks_with_length(
+    range: &RangeInclusive<usize>,
+    length: u32,
+    repetitions: u32,
+) -> (RangeInclusive<u128>, u128) {
+    let block_length = length / repetitions;
+    let block_base = 10_u128.pow(block_length);
+    let multiplier = (10_u128.pow(length) - 1) / (block_base - 1);
+    let first_block = (block_base / 10).max((*range.start() as u128).div_ceil(multiplier));
+    let last_block = (block_base - 1).min(*range.end() as u128 / multiplier);
+    (first_block..=last_block, multiplier)
+}
+
+/// All numbers with exactly `length` digits in `range` that are a block of `length / repetitions`
+/// digits repeated `repetitions` times.
+fn repeated_with_length(
+    range: &RangeInclusive<usize>,
+    length: u32,
+    repetitions: u32,
+) -> impl Iterator<Item = usize> + use<> {
+    let (blocks, multiplier) = blocks_with_length(range, length, repetitions);
+    blocks.map(move |block| {
+        usize::try_from(block * multiplier).expect("Value is bounded by the range end")
+    })
+}
+
+/// All numbers in `range` that consist of one block of digits repeated exactly `repetitions` times,
+/// e.g. `1212` or `121212` for the block `12`. Blocks never have a leading zero.
+///
+/// The numbers are generated in ascending order without looking at any non-matching number.
+///
+/// # Panics
+/// If `repetitions` is less than 2.
+pub fn repeated_in_range(
+    range: &RangeInclusive<usize>,
+    repetitions: u32,
+) -> impl Iterator<Item = usize> + use<> {
+    assert!(repetitions >= 2, "A repetition needs at least two blocks");
+    let range = range.clone();
+    (digit_count(*range.start())..=digit_count(*range.end()))
+        .filter(move |length| length.is_multiple_of(repetitions))
+        .flat_map(move |length| repeated_with_length(&range, length, repetitions))
+}
+
+/// Number and sum of the numbers [`repeated_with_length`] lists, without looking at them one by one.
+fn count_and_sum_with_length(
+    range: &RangeInclusive<usize>,
+    length: u32,
+    repetitions: u32,
+) -> (u128, u128) {
+    let (blocks, multiplier) = blocks_with_length(range, length, repetitions);
+    let (first, last) = blocks.into_inner();
+    if first > last {
+        return (0, 0);
+    }
+    let count = last - first + 1;
+    (count, multiplier * (first + last) * count / 2)
+}
+
+/// Sum of all numbers in `range` that consist of one block of digits repeated exactly `repetitions`
+/// times.
+///
+/// # Panics
+/// If `repetitions` is less than 2.
+#[must_use]
+pub fn sum_repeated_in_range(range: &RangeInclusive<usize>, repetitions: u32) -> u128 {
+    assert!(repetitions >= 2, "A repetition needs at least two blocks");
+    (digit_count(*range.start())..=digit_count(*range.end()))
+        .filter(|length| length.is_multiple_of(repetitions))
+        .map(|length| count_and_sum_with_length(range, length, repetitions).1)
+        .sum()
+}
+
+/// Combines the count or sum (as picked by `value`) of the numbers in `range` that repeat a block
+/// exactly `d` times into the one of all numbers that repeat a block at least twice.
+///
+/// A number like `111111` is `1` six times, `11` three times and `111` twice. To count it only once,
+/// the sets of numbers repeating exactly `d` times are combined by inclusion-exclusion over the
+/// divisors `d` of the digit count, which is exactly what the Möbius function describes.
+fn sum_over_repeating(range: &RangeInclusive<usize>, value: impl Fn((u128, u128)) -> u128) -> i128 {
+    let mut total = 0;
+    for length in digit_count(*range.start())..=digit_count(*range.end()) {
+        for repetitions in (2..=length).filter(|d| length.is_multiple_of(*d)) {
+            let sign = -i128::from(moebius(repetitions));
+            if sign != 0 {
+                let count_and_sum = count_and_sum_with_length(range, length, repetitions);
+                total += sign * value(count_and_sum).cast_signed();
+            }
+        }
+    }
+    total
+}
+
+/// Number of values in `range` that are a block of digits repeated at least twice.
+#[must_use]
+pub fn count_repeating_in_range(range: &RangeInclusive<usize>) -> u128 {
+    sum_over_repeating(range, |(count, _)| count).cast_unsigned()
+}
+
+/// Sum of all values in `range` that are a block of digits repeated at least twice, each counted once.
+#[must_use]
+pub fn sum_repeating_in_range(range: &RangeInclusive<usize>) -> u128 {
+    sum_over_repeating(range, |(_, sum)| sum).cast_unsigned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_in_range() {
+        assert_eq!(
+            repeated_in_range(&(1..=100), 2).collect::<Vec<_>>(),
+            vec![11, 22, 33, 44, 55, 66, 77, 88, 99]
+        );
+        assert_eq!(
+            repeated_in_range(&(100_000..=200_000), 3).collect::<Vec<_>>(),
+            vec![
+                101_010, 111_111, 121_212, 131_313, 141_414, 151_515, 161_616, 171_717, 181_818,
+                191_919
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sum_repeated_in_range() {
+        for range in [1..=100, 100_000..=200_000, 12_345..=987_654_321_012] {
+            for repetitions in [2, 3] {
+                assert_eq!(
+                    sum_repeated_in_range(&range, repetitions),
+                    repeated_in_range(&range, repetitions)
+                        .map(|n| n as u128)
+                        .sum(),
+                    "{range:?} {repetitions}"
+                );
+            }
+        }
+        // Every number below 2^64 fits into a u128 more than 2^32 times
+        assert!(sum_repeated_in_range(&(0..=usize::MAX), 2) > 0);
+    }
+
+    #[test]
+    fn test_repeating_counted_once() {
+        assert_eq!(count_repeating_in_range(&(111_111..=111_111)), 1);
+        assert_eq!(sum_repeating_in_range(&(111_111..=111_111)), 111_111);
+        assert_eq!(count_repeating_in_range(&(0..=9)), 0);
+        assert_eq!(count_repeating_in_range(&(1..=1_000)), 9 + 9);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        fn is_repeating(n: usize) -> bool {
+            let digits = n.to_string().into_bytes();
+            (1..digits.len()).any(|block| {
+                digits.len().is_multiple_of(block)
+                    && digits.chunks(block).all(|chunk| chunk == &digits[..block])
+            })
+        }
+        for range in [
+            0..=5_000,
+            99_990..=1_010_101,
+            usize::MAX - 5_000..=usize::MAX,
+        ] {
+            let expected: Vec<usize> = range.clone().filter(|n| is_repeating(*n)).collect();
+            assert_eq!(count_repeating_in_range(&range), expected.len() as u128);
+            assert_eq!(
+                sum_repeating_in_range(&range),
+                expected.iter().map(|&n| n as u128).sum::<u128>()
+            );
+        }
+    }
+}
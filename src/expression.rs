@@ -0,0 +1,192 @@
+//! A small expression engine: an operator folded over a list of operands from left to right, with
+//! checked arithmetic on `i128`.
+
+use std::fmt::Display;
+
+/// Binary operators, written by their symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    /// Division rounding towards zero, like Rust's `/`
+    Divide,
+    /// Remainder with the sign of the dividend, like Rust's `%`
+    Remainder,
+    Power,
+    Min,
+    Max,
+}
+
+impl Operator {
+    pub const ALL: [Operator; 8] = [
+        Operator::Add,
+        Operator::Subtract,
+        Operator::Multiply,
+        Operator::Divide,
+        Operator::Remainder,
+        Operator::Power,
+        Operator::Min,
+        Operator::Max,
+    ];
+
+    #[must_use]
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Remainder => "%",
+            Operator::Power => "^",
+            Operator::Min => "min",
+            Operator::Max => "max",
+        }
+    }
+
+    /// The operator written as `symbol`, with `<` and `>` as short forms of `min` and `max` that
+    /// fit into a single column.
+    #[must_use]
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "<" => Some(Operator::Min),
+            ">" => Some(Operator::Max),
+            _ => Operator::ALL
+                .into_iter()
+                .find(|operator| operator.symbol() == symbol),
+        }
+    }
+
+    /// `a` combined with `b`.
+    ///
+    /// # Errors
+    /// If the result doesn't fit into an `i128`, `b` is zero for a division or remainder, or `b` is
+    /// a negative exponent.
+    pub fn apply(self, a: i128, b: i128) -> Result<i128, EvalError> {
+        match self {
+            Operator::Add => a.checked_add(b).ok_or(EvalError::Overflow),
+            Operator::Subtract => a.checked_sub(b).ok_or(EvalError::Overflow),
+            Operator::Multiply => a.checked_mul(b).ok_or(EvalError::Overflow),
+            Operator::Divide | Operator::Remainder if b == 0 => Err(EvalError::DivisionByZero),
+            Operator::Divide => a.checked_div(b).ok_or(EvalError::Overflow),
+            Operator::Remainder => a.checked_rem(b).ok_or(EvalError::Overflow),
+            Operator::Power => power(a, b),
+            Operator::Min => Ok(a.min(b)),
+            Operator::Max => Ok(a.max(b)),
+        }
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.symbol())
+    }
+}
+
+/// Why an expression has no value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalError {
+    NoOperands,
+    Overflow,
+    DivisionByZero,
+    NegativeExponent,
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EvalError::NoOperands => "there are no operands",
+            EvalError::Overflow => "the result doesn't fit into 128 bits",
+            EvalError::DivisionByZero => "it divides by zero",
+            EvalError::NegativeExponent => "the exponent is negative",
+        })
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// `base` to the power of `exponent`, which may be too large for `u32` if the base is 0, 1 or -1.
+fn power(base: i128, exponent: i128) -> Result<i128, EvalError> {
+    if exponent < 0 {
+        return Err(EvalError::NegativeExponent);
+    }
+    match (base, u32::try_from(exponent)) {
+        (_, Ok(exponent)) => base.checked_pow(exponent).ok_or(EvalError::Overflow),
+        (0 | 1, Err(_)) => Ok(base),
+        (-1, Err(_)) => Ok(if exponent % 2 == 0 { 1 } else { -1 }),
+        _ => Err(EvalError::Overflow),
+    }
+}
+
+/// `operator` applied to the operands from left to right, e.g. `(a - b) - c` for `-`.
+///
+/// # Errors
+/// If there are no operands or any step fails, see [`Operator::apply`].
+pub fn evaluate(operator: Operator, operands: &[i128]) -> Result<i128, EvalError> {
+    let (first, rest) = operands.split_first().ok_or(EvalError::NoOperands)?;
+    rest.iter()
+        .try_fold(*first, |value, &operand| operator.apply(value, operand))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let operands = [12, 5, 2];
+        let values: Vec<Result<i128, EvalError>> = Operator::ALL
+            .into_iter()
+            .map(|operator| evaluate(operator, &operands))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                Ok(19),
+                Ok(5),
+                Ok(120),
+                Ok(1),
+                Ok(0),
+                Ok(61_917_364_224),
+                Ok(2),
+                Ok(12)
+            ]
+        );
+        assert_eq!(evaluate(Operator::Add, &[5]), Ok(5));
+        assert_eq!(evaluate(Operator::Add, &[]), Err(EvalError::NoOperands));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            evaluate(Operator::Multiply, &[i128::MAX, 2]),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            evaluate(Operator::Remainder, &[5, 0]),
+            Err(EvalError::DivisionByZero)
+        );
+        assert_eq!(
+            evaluate(Operator::Divide, &[i128::MIN, -1]),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            evaluate(Operator::Power, &[2, -1]),
+            Err(EvalError::NegativeExponent)
+        );
+        assert_eq!(evaluate(Operator::Power, &[-1, 1 << 40]), Ok(1));
+        assert_eq!(
+            evaluate(Operator::Power, &[2, 1 << 40]),
+            Err(EvalError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_symbols() {
+        for operator in Operator::ALL {
+            assert_eq!(Operator::from_symbol(operator.symbol()), Some(operator));
+        }
+        assert_eq!(Operator::from_symbol("<"), Some(Operator::Min));
+        assert_eq!(Operator::from_symbol("x"), None);
+    }
+}
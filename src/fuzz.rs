@@ -0,0 +1,310 @@
+//! Fuzzing: arbitrary bytes are fed through a day's parser and solutions, and every panic that isn't
+//! the day rejecting the input is reported as a crash.
+//!
+//! [`fuzz_day`] is the entry point for external fuzzers, [`run`] a small mutation fuzzer that works
+//! offline from seed inputs.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::Once,
+};
+
+use crate::{
+    days::get_day,
+    generate::{Rng, generate},
+    solution::{Context, Part},
+    utils::read_input,
+};
+
+/// Directory the crashing inputs of a day are kept in, so they are replayed by the tests.
+#[must_use]
+pub fn corpus_dir(day: usize) -> PathBuf {
+    PathBuf::from(format!("fuzz/corpus/{day:02}"))
+}
+
+/// Start of the panic message days use for inputs they can't solve, see [`crate::parse::reject`].
+const REJECTED: &str = "Invalid input";
+
+/// Bytes that mean something to at least one parser, so mutations often produce almost valid input.
+const INTERESTING_BYTES: &[u8] = b"0123456789,-+*/%<>:x#.@^S\n LR[](){}";
+
+/// Numbers around the limits of the integer types the days use.
+const INTERESTING_NUMBERS: &[&str] = &[
+    "0",
+    "1",
+    "127",
+    "256",
+    "4294967296",
+    "9223372036854775807",
+    "18446744073709551615",
+    "99999999999999999999999",
+];
+
+/// A panic while a day handled an input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crash {
+    /// What the day was doing: `part 1`, `part 2` or `validate`
+    pub stage: String,
+    pub message: String,
+    /// Source location of the panic, if it happened on the fuzzing thread
+    pub location: Option<String>,
+    /// Assumptions of the day the validator says the input breaks
+    pub broken: Vec<String>,
+    pub input: Vec<u8>,
+}
+
+impl Display for Crash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} panicked", self.stage)?;
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        write!(f, ": {}", self.message)?;
+        if !self.broken.is_empty() {
+            write!(f, " (the input breaks: {})", self.broken.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// Whether panics on this thread are caught by the fuzzer and shouldn't be printed
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+    /// Location of the last caught panic on this thread
+    static LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Installs a panic hook that keeps caught panics quiet and remembers where they happened.
+fn install_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING.get() {
+                LOCATION.set(info.location().map(ToString::to_string));
+            } else {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/// Runs `f`, turning a panic into its message and location.
+fn catch<T>(f: impl FnOnce() -> T) -> Result<T, (String, Option<String>)> {
+    install_hook();
+    CATCHING.set(true);
+    LOCATION.set(None);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.set(false);
+    result.map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Panicked without a message".to_string());
+        (message, LOCATION.take())
+    })
+}
+
+/// Feeds `data` to both parts of `day`.
+///
+/// Panics with the message of [`crate::parse::reject`] count as rejected, not as crash. The
+/// validator doesn't decide what is fuzzed, as the parts run on unvalidated input as well, it only
+/// labels crashes with the assumptions the input breaks.
+///
+/// # Errors
+/// The first panic that isn't a rejection.
+///
+/// # Panics
+/// If `day` isn't implemented.
+pub fn fuzz_day(day: usize, data: &[u8]) -> Result<(), Crash> {
+    let solution = get_day(day).expect("Fuzzed day is implemented");
+    let input = String::from_utf8_lossy(data);
+    let ctx = Context::new(solution.as_ref(), false);
+    let crash = |stage: &str, (message, location): (String, Option<String>)| {
+        let broken = catch(|| solution.validate(&input, &ctx)).map_or_else(
+            |_| vec!["The validator doesn't panic".to_string()],
+            |violations| {
+                let mut broken: Vec<String> = Vec::new();
+                for violation in violations {
+                    if !broken.iter().any(|known| known == violation.assumption) {
+                        broken.push(violation.assumption.to_string());
+                    }
+                }
+                broken
+            },
+        );
+        Crash {
+            stage: stage.to_string(),
+            message,
+            location,
+            broken,
+            input: data.to_vec(),
+        }
+    };
+    for part in [Part::One, Part::Two] {
+        match catch(|| solution.run(&input, &ctx, part)) {
+            Err((message, _)) if message.starts_with(REJECTED) => {}
+            Err(panic) => return Err(crash(&format!("part {part}"), panic)),
+            Ok(_) => {}
+        }
+    }
+    catch(|| solution.validate(&input, &ctx)).map_err(|panic| crash("validate", panic))?;
+    Ok(())
+}
+
+/// Changes `data` by a few random edits: replacing, inserting, deleting or duplicating bytes, or
+/// replacing a number by one near the limits of an integer type.
+pub fn mutate(data: &mut Vec<u8>, rng: &mut Rng) {
+    for _ in 0..rng.range(1..=4) {
+        let at = rng.range(0..=data.len());
+        match rng.range(0..=4) {
+            0 if at < data.len() => data[at] = *rng.pick(INTERESTING_BYTES),
+            1 => data.insert(at, *rng.pick(INTERESTING_BYTES)),
+            2 if at < data.len() => {
+                let end = rng.range(at + 1..=data.len().min(at + 8));
+                data.drain(at..end);
+            }
+            3 if at < data.len() => {
+                let end = rng.range(at + 1..=data.len().min(at + 32));
+                let span = data[at..end].to_vec();
+                let to = rng.range(0..=data.len());
+                data.splice(to..to, span);
+            }
+            4 => {
+                let start = data[..at]
+                    .iter()
+                    .rposition(|b| !b.is_ascii_digit())
+                    .map_or(0, |i| i + 1);
+                let end = data[at..]
+                    .iter()
+                    .position(|b| !b.is_ascii_digit())
+                    .map_or(data.len(), |i| at + i);
+                let number = rng.pick(INTERESTING_NUMBERS).bytes();
+                data.splice(start..end, number);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Inputs to start mutating from: the examples of `day` and a small generated input.
+#[must_use]
+pub fn seeds(day: usize) -> Vec<Vec<u8>> {
+    let mut seeds: Vec<Vec<u8>> = [1, 2]
+        .into_iter()
+        .filter_map(|part| read_input(day, true, part))
+        .chain(generate(day, 5, 0))
+        .map(String::into_bytes)
+        .collect();
+    seeds.dedup();
+    seeds
+}
+
+/// Fuzzes `day` for `iterations` mutations of the `seeds` and returns the crashes, one per distinct
+/// panic location (or message, if the location is unknown).
+pub fn run(day: usize, seeds: &[Vec<u8>], iterations: usize, rng: &mut Rng) -> Vec<Crash> {
+    let mut crashes: Vec<Crash> = Vec::new();
+    for _ in 0..iterations {
+        let mut data = rng.pick(seeds).clone();
+        mutate(&mut data, rng);
+        if let Err(crash) = fuzz_day(day, &data) {
+            let known = crashes.iter().any(|known| {
+                (&known.location, &known.message) == (&crash.location, &crash.message)
+                    || (known.location.is_some() && known.location == crash.location)
+            });
+            if !known {
+                crashes.push(crash);
+            }
+        }
+    }
+    crashes
+}
+
+/// Stores the input of `crash` in `dir`, usually the [`corpus_dir`] of its day, and returns its
+/// path. The file is named after a hash of the input, so the same input is only stored once.
+///
+/// # Errors
+/// If the directory or file can't be written.
+pub fn save(dir: &Path, crash: &Crash) -> std::io::Result<PathBuf> {
+    let hash = crash
+        .input
+        .iter()
+        .fold(0xCBF2_9CE4_8422_2325_u64, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3)
+        });
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{hash:016x}.txt"));
+    fs::write(&path, &crash.input)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzz_days() {
+        let mut failures = Vec::new();
+        for day in 1..=12 {
+            for crash in run(day, &seeds(day), 500, &mut Rng::new(0)) {
+                // The corpus is only added to on purpose, by the `fuzz` command
+                let dir = std::env::temp_dir().join(format!("fuzz-crashes/{day:02}"));
+                let path = save(&dir, &crash).unwrap();
+                failures.push(format!(
+                    "Day {day}: {crash}, input saved to {}",
+                    path.display()
+                ));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_corpus_does_not_crash() {
+        for day in 1..=12 {
+            let Ok(entries) = fs::read_dir(corpus_dir(day)) else {
+                continue;
+            };
+            for entry in entries {
+                let path = entry.unwrap().path();
+                let data = fs::read(&path).unwrap();
+                if let Err(crash) = fuzz_day(day, &data) {
+                    panic!("Day {day}: {crash} on {}", path.display());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rejections_are_no_crashes() {
+        assert_eq!(fuzz_day(1, b"X12\n"), Ok(()));
+        assert_eq!(fuzz_day(5, b"3-x\n\n1\n"), Ok(()));
+        // Inputs the validator flags still run through both parts
+        assert_eq!(fuzz_day(8, b"9223372036854775808,0,0\n0,0,0\n"), Ok(()));
+        assert_eq!(fuzz_day(9, b"1,1\n5,3\n5,1\n"), Ok(()));
+        assert_eq!(fuzz_day(10, b"[.#] (2) {1,1}\n"), Ok(()));
+        let crash = catch(|| panic!("Boom")).unwrap_err();
+        assert_eq!(crash.0, "Boom");
+        assert!(crash.1.unwrap().starts_with("src/fuzz.rs"));
+    }
+
+    #[test]
+    fn test_crash_names_broken_assumptions() {
+        let crash = Crash {
+            stage: "part 2".to_string(),
+            message: "Boom".to_string(),
+            location: None,
+            broken: vec!["The grid has cells".to_string()],
+            input: Vec::new(),
+        };
+        assert_eq!(
+            crash.to_string(),
+            "part 2 panicked: Boom (the input breaks: The grid has cells)"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::ops::RangeInclusive;
+
+use crate::days::get_day;
+
+/// Small deterministic random number generator (`SplitMix64`), so generated inputs only depend on
+/// their seed.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `range`, every one about equally likely.
+    ///
+    /// # Panics
+    /// If the range is empty.
+    pub fn range(&mut self, range: RangeInclusive<usize>) -> usize {
+        let (start, end) = range.into_inner();
+        assert!(
+            start <= end,
+            "Can't pick from the empty range {start}..={end}"
+        );
+        let span = u128::from(u64::try_from(end - start).expect("usize fits into u64")) + 1;
+        let offset = (u128::from(self.next_u64()) * span) >> 64;
+        start + usize::try_from(offset).expect("Offset is smaller than the span")
+    }
+
+    /// True in `numerator` out of `denominator` cases.
+    pub fn chance(&mut self, numerator: usize, denominator: usize) -> bool {
+        self.range(1..=denominator) <= numerator
+    }
+
+    /// # Panics
+    /// If `items` is empty.
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.range(0..=items.len() - 1)]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.range(0..=i));
+        }
+    }
+
+    /// A number with exactly `digits` digits, none of them 0.
+    #[must_use]
+    pub fn digits(&mut self, digits: usize) -> String {
+        (0..digits)
+            .map(|_| char::from(b"123456789"[self.range(0..=8)]))
+            .collect()
+    }
+}
+
+/// Generates a valid input for `day` with about `size` items (lines, ranges, machines, ...), that only
+/// depends on `seed`.
+///
+/// Returns `None` if the day has no generator.
+#[must_use]
+pub fn generate(day: usize, size: usize, seed: u64) -> Option<String> {
+    get_day(day)?.generate(size, &mut Rng::new(seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solution::{self, Context, Part};
+
+    #[test]
+    fn test_rng() {
+        let mut rng = Rng::new(42);
+        let numbers: Vec<usize> = (0..1000).map(|_| rng.range(3..=7)).collect();
+        assert!(numbers.iter().all(|n| (3..=7).contains(n)));
+        assert!((3..=7).all(|n| numbers.contains(&n)));
+        assert_eq!(Rng::new(42).next_u64(), Rng::new(42).next_u64());
+        assert_ne!(Rng::new(42).next_u64(), Rng::new(43).next_u64());
+        let mut items: Vec<usize> = (0..10).collect();
+        rng.shuffle(&mut items);
+        items.sort_unstable();
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_generated_inputs_are_valid() {
+        for day in 1..=12 {
+            let solution = get_day(day).unwrap();
+            for seed in 0..3 {
+                let input = generate(day, 20, seed).unwrap();
+                assert_eq!(generate(day, 20, seed), Some(input.clone()));
+                let ctx = Context::new(solution.as_ref(), false);
+                assert_eq!(
+                    solution.validate(&input, &ctx),
+                    vec![],
+                    "Day {day}, seed {seed}"
+                );
+                assert!(
+                    solution.part1(&input, &ctx).is_some(),
+                    "Day {day}, seed {seed}"
+                );
+                // Day 12 has no part 2
+                assert_eq!(
+                    solution.part2(&input, &ctx).is_some(),
+                    day != 12,
+                    "Day {day}, seed {seed}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_implementations_agree() {
+        for day in 1..=12 {
+            let solution = get_day(day).unwrap();
+            let ctx = Context::new(solution.as_ref(), false);
+            for seed in 0..3 {
+                let input = generate(day, 20, seed).unwrap();
+                for part in [Part::One, Part::Two] {
+                    let answers = solution::answers(solution.as_ref(), &input, &ctx, part);
+                    assert!(
+                        answers.iter().all(|(_, answer)| *answer == answers[0].1),
+                        "Day {day}.{part}, seed {seed}: {answers:?}"
+                    );
+                }
+            }
+        }
+    }
+}
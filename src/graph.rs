@@ -0,0 +1,295 @@
+use std::{borrow::Borrow, collections::HashMap, collections::VecDeque, hash::Hash};
+
+pub type NodeId = usize;
+
+/// The nodes of a strongly connected component that prevents a topological order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle(pub Vec<NodeId>);
+
+/// A directed graph whose node keys are interned to dense ids, so algorithms can work on vectors
+/// instead of hash maps.
+#[derive(Debug, Clone)]
+pub struct Graph<K> {
+    keys: Vec<K>,
+    ids: HashMap<K, NodeId>,
+    successors: Vec<Vec<NodeId>>,
+    predecessors: Vec<Vec<NodeId>>,
+}
+
+impl<K> Default for Graph<K> {
+    fn default() -> Self {
+        Self {
+            keys: Vec::new(),
+            ids: HashMap::new(),
+            successors: Vec::new(),
+            predecessors: Vec::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> Graph<K> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the id of `key`, adding it as a new node if it isn't known yet.
+    pub fn intern(&mut self, key: K) -> NodeId {
+        if let Some(&id) = self.ids.get(&key) {
+            return id;
+        }
+        let id = self.keys.len();
+        self.keys.push(key.clone());
+        self.ids.insert(key, id);
+        self.successors.push(Vec::new());
+        self.predecessors.push(Vec::new());
+        id
+    }
+
+    pub fn id<Q>(&self, key: &Q) -> Option<NodeId>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.ids.get(key).copied()
+    }
+
+    #[must_use]
+    pub fn key(&self, id: NodeId) -> &K {
+        &self.keys[id]
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn add_edge(&mut self, from: NodeId, to: NodeId) {
+        self.successors[from].push(to);
+        self.predecessors[to].push(from);
+    }
+
+    #[must_use]
+    pub fn successors(&self, id: NodeId) -> &[NodeId] {
+        &self.successors[id]
+    }
+
+    #[must_use]
+    pub fn predecessors(&self, id: NodeId) -> &[NodeId] {
+        &self.predecessors[id]
+    }
+
+    fn flood(&self, start: NodeId, edges: &[Vec<NodeId>]) -> Vec<bool> {
+        let mut seen = vec![false; self.len()];
+        seen[start] = true;
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            for &next in &edges[node] {
+                if !seen[next] {
+                    seen[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Marks every node that can be reached from `start`.
+    #[must_use]
+    pub fn reachable_from(&self, start: NodeId) -> Vec<bool> {
+        self.flood(start, &self.successors)
+    }
+
+    /// Marks every node from which `goal` can be reached.
+    #[must_use]
+    pub fn reaching(&self, goal: NodeId) -> Vec<bool> {
+        self.flood(goal, &self.predecessors)
+    }
+
+    /// Marks every node that lies on at least one path from `start` to `goal`.
+    #[must_use]
+    pub fn nodes_between(&self, start: NodeId, goal: NodeId) -> Vec<bool> {
+        self.reachable_from(start)
+            .into_iter()
+            .zip(self.reaching(goal))
+            .map(|(from_start, to_goal)| from_start && to_goal)
+            .collect()
+    }
+
+    /// Copy of the graph without any edges touching a node that isn't kept.
+    ///
+    /// Ids stay the same, dropped nodes just become isolated.
+    #[must_use]
+    pub fn retain_nodes(&self, keep: &[bool]) -> Self {
+        let mut pruned = Self {
+            keys: self.keys.clone(),
+            ids: self.ids.clone(),
+            successors: vec![Vec::new(); self.len()],
+            predecessors: vec![Vec::new(); self.len()],
+        };
+        for (from, targets) in self.successors.iter().enumerate() {
+            for &to in targets {
+                if keep[from] && keep[to] {
+                    pruned.add_edge(from, to);
+                }
+            }
+        }
+        pruned
+    }
+
+    /// Orders all nodes so that every edge points forward using Kahn's algorithm.
+    ///
+    /// # Errors
+    /// If the graph contains a cycle, one of the strongly connected components forming it.
+    pub fn topological_order(&self) -> Result<Vec<NodeId>, Cycle> {
+        let mut pending: Vec<usize> = self.predecessors.iter().map(Vec::len).collect();
+        let mut queue: VecDeque<NodeId> = (0..self.len()).filter(|&id| pending[id] == 0).collect();
+        let mut order = Vec::with_capacity(self.len());
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            for &next in &self.successors[node] {
+                pending[next] -= 1;
+                if pending[next] == 0 {
+                    queue.push_back(next);
+                }
+            }
+        }
+        if order.len() == self.len() {
+            Ok(order)
+        } else {
+            let cycle = self
+                .strongly_connected_components()
+                .into_iter()
+                .find(|component| {
+                    component.len() > 1 || self.successors[component[0]].contains(&component[0])
+                })
+                .unwrap_or_default();
+            Err(Cycle(cycle))
+        }
+    }
+
+    /// Splits the graph into strongly connected components using Tarjan's algorithm.
+    ///
+    /// The recursion is unrolled into an explicit stack, so deep graphs can't overflow the call stack.
+    /// Components are returned in reverse topological order.
+    #[must_use]
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        const UNVISITED: usize = usize::MAX;
+        let mut index = vec![UNVISITED; self.len()];
+        let mut low_link = vec![0; self.len()];
+        let mut on_stack = vec![false; self.len()];
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+        let mut next_index = 0;
+        // Node and index of the next successor to look at
+        let mut call_stack: Vec<(NodeId, usize)> = Vec::new();
+
+        for root in 0..self.len() {
+            if index[root] != UNVISITED {
+                continue;
+            }
+            call_stack.push((root, 0));
+            while let Some((node, edge)) = call_stack.last_mut() {
+                let node = *node;
+                if *edge == 0 {
+                    index[node] = next_index;
+                    low_link[node] = next_index;
+                    next_index += 1;
+                    stack.push(node);
+                    on_stack[node] = true;
+                }
+                if let Some(&next) = self.successors[node].get(*edge) {
+                    *edge += 1;
+                    if index[next] == UNVISITED {
+                        call_stack.push((next, 0));
+                    } else if on_stack[next] {
+                        low_link[node] = low_link[node].min(index[next]);
+                    }
+                    continue;
+                }
+                call_stack.pop();
+                if let Some(&(parent, _)) = call_stack.last() {
+                    low_link[parent] = low_link[parent].min(low_link[node]);
+                }
+                if low_link[node] == index[node] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&'static str, &'static str)]) -> Graph<&'static str> {
+        let mut graph = Graph::new();
+        for &(from, to) in edges {
+            let (from, to) = (graph.intern(from), graph.intern(to));
+            graph.add_edge(from, to);
+        }
+        graph
+    }
+
+    #[test]
+    fn test_interning() {
+        let graph = graph(&[("a", "b"), ("b", "c"), ("a", "c")]);
+        assert_eq!(graph.len(), 3);
+        assert_eq!(graph.id("b"), Some(1));
+        assert_eq!(graph.id("x"), None);
+        assert_eq!(*graph.key(2), "c");
+        assert_eq!(graph.successors(0), &[1, 2]);
+        assert_eq!(graph.predecessors(2), &[1, 0]);
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let graph = graph(&[("c", "d"), ("a", "b"), ("b", "c"), ("a", "c")]);
+        let order = graph.topological_order().unwrap();
+        let position = |name| order.iter().position(|&id| id == graph.id(name).unwrap());
+        assert!(position("a") < position("b"));
+        assert!(position("b") < position("c"));
+        assert!(position("c") < position("d"));
+    }
+
+    #[test]
+    fn test_cycles() {
+        let graph = graph(&[("a", "b"), ("b", "c"), ("c", "b"), ("c", "d"), ("d", "d")]);
+        let mut components = graph.strongly_connected_components();
+        for component in &mut components {
+            component.sort_unstable();
+        }
+        assert_eq!(components, vec![vec![3], vec![1, 2], vec![0]]);
+        assert!(graph.topological_order().is_err());
+
+        let between = graph.nodes_between(0, 1);
+        assert_eq!(between, vec![true, true, true, false]);
+        let Err(Cycle(mut cycle)) = graph.retain_nodes(&between).topological_order() else {
+            panic!("b and c still form a cycle");
+        };
+        cycle.sort_unstable();
+        assert_eq!(cycle, vec![1, 2]);
+        assert!(
+            graph
+                .retain_nodes(&[true, false, true, true])
+                .retain_nodes(&[true, true, true, false])
+                .topological_order()
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Column layouts: text whose lines form blocks of columns, separated by columns that are blank in
+//! every line, like a worksheet with one problem per block.
+//!
+//! Columns count bytes. Lines may have different lengths, a line too short for a column counts as
+//! blank there.
+
+use std::ops::Range;
+
+/// Text split into lines and blocks of columns.
+#[derive(Debug, Clone)]
+pub struct Layout<'a> {
+    lines: Vec<&'a str>,
+    blocks: Vec<Range<usize>>,
+}
+
+impl<'a> Layout<'a> {
+    /// Splits `text` into lines, without blank lines at the end, and finds the blocks.
+    #[must_use]
+    pub fn new(text: &'a str) -> Self {
+        let mut lines: Vec<&str> = text.lines().collect();
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+        let mut filled = vec![false; width];
+        for line in &lines {
+            for (col, byte) in line.bytes().enumerate() {
+                filled[col] |= !byte.is_ascii_whitespace();
+            }
+        }
+        let mut blocks = Vec::new();
+        let mut start = None;
+        for (col, &filled) in filled.iter().chain([&false]).enumerate() {
+            match (start, filled) {
+                (None, true) => start = Some(col),
+                (Some(from), false) => {
+                    blocks.push(from..col);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        Self { lines, blocks }
+    }
+
+    #[must_use]
+    pub fn lines(&self) -> &[&'a str] {
+        &self.lines
+    }
+
+    /// The blocks from left to right.
+    pub fn blocks(&self) -> impl Iterator<Item = Block<'_, 'a>> {
+        self.blocks.iter().map(|columns| Block {
+            layout: self,
+            columns: columns.clone(),
+        })
+    }
+
+    /// The character at byte `col` of line `row` as a slice of the text, or the empty end of the
+    /// line if it's shorter, e.g. to point errors at.
+    ///
+    /// # Panics
+    /// If there's no line `row`.
+    #[must_use]
+    pub fn cell(&self, row: usize, col: usize) -> &'a str {
+        let line = self.lines[row];
+        let start = line.floor_char_boundary(col);
+        let end = line.ceil_char_boundary(col + 1);
+        &line[start..end]
+    }
+
+    /// Byte `col` of every line in `rows` from top to bottom, with a space for lines too short.
+    pub fn column(&self, col: usize, rows: Range<usize>) -> impl Iterator<Item = u8> + '_ {
+        self.lines[rows]
+            .iter()
+            .map(move |line| line.as_bytes().get(col).copied().unwrap_or(b' '))
+    }
+}
+
+/// Columns that aren't blank in at least one line, between blank ones.
+#[derive(Debug, Clone)]
+pub struct Block<'l, 'a> {
+    layout: &'l Layout<'a>,
+    columns: Range<usize>,
+}
+
+impl<'a> Block<'_, 'a> {
+    #[must_use]
+    pub fn columns(&self) -> Range<usize> {
+        self.columns.clone()
+    }
+
+    /// The part of line `row` in the block's columns as a slice of the text, cut short where the
+    /// line ends.
+    ///
+    /// Blocks are surrounded by blank columns, so this never splits a character.
+    ///
+    /// # Panics
+    /// If there's no line `row`.
+    #[must_use]
+    pub fn row(&self, row: usize) -> &'a str {
+        let line = self.layout.lines[row];
+        let end = self.columns.end.min(line.len());
+        &line[self.columns.start.min(end)..end]
+    }
+
+    /// The block read row by row, see [`Block::row`].
+    pub fn rows(&self) -> impl Iterator<Item = &'a str> + '_ {
+        (0..self.layout.lines.len()).map(|row| self.row(row))
+    }
+
+    /// The block read column by column, every column with its index and bytes in `rows` from top to
+    /// bottom, see [`Layout::column`].
+    pub fn read_columns(
+        &self,
+        rows: Range<usize>,
+    ) -> impl Iterator<Item = (usize, impl Iterator<Item = u8> + '_)> + '_ {
+        self.columns()
+            .map(move |col| (col, self.layout.column(col, rows.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        let layout = Layout::new("12  3\n 4 56\n+   *  \n\n");
+        assert_eq!(layout.lines().len(), 3);
+        let blocks: Vec<Block> = layout.blocks().collect();
+        assert_eq!(
+            blocks.iter().map(Block::columns).collect::<Vec<_>>(),
+            vec![0..2, 3..5]
+        );
+        assert_eq!(blocks[0].rows().collect::<Vec<_>>(), vec!["12", " 4", "+ "]);
+        assert_eq!(blocks[1].rows().collect::<Vec<_>>(), vec![" 3", "56", " *"]);
+        let columns: Vec<(usize, Vec<u8>)> = blocks[1]
+            .read_columns(0..2)
+            .map(|(col, cells)| (col, cells.collect()))
+            .collect();
+        assert_eq!(columns, vec![(3, b" 5".to_vec()), (4, b"36".to_vec())]);
+    }
+
+    #[test]
+    fn test_ragged_lines() {
+        let layout = Layout::new("1 22\n333\n+");
+        let blocks: Vec<Block> = layout.blocks().collect();
+        // Column 1 is blank in the first line only, so all columns form one block
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(
+            blocks[0].rows().collect::<Vec<_>>(),
+            vec!["1 22", "333", "+"]
+        );
+        assert_eq!(layout.column(3, 0..3).collect::<Vec<_>>(), b"2  ");
+        assert_eq!(layout.cell(2, 3), "");
+        assert_eq!(Layout::new("1  2\n\n").blocks().count(), 2);
+        assert_eq!(Layout::new("").blocks().count(), 0);
+    }
+
+    #[test]
+    fn test_cell_keeps_characters_whole() {
+        let layout = Layout::new("ä1");
+        assert_eq!(layout.cell(0, 1), "ä");
+        assert_eq!(layout.cell(0, 2), "1");
+    }
+}
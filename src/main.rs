@@ -1,13 +1,32 @@
 #![deny(clippy::pedantic)]
-use std::time::Duration;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+pub mod allocations;
+pub mod animation;
+pub mod beam;
+pub mod bytes;
 pub mod days;
+pub mod digits;
+pub mod expression;
+pub mod fuzz;
+pub mod generate;
+pub mod graph;
+pub mod layout;
+pub mod observe;
+pub mod parse;
+pub mod polygon;
+#[cfg(test)]
+pub mod property;
+pub mod scaling;
 pub mod solution;
 pub mod utils;
+pub mod visualize;
 
+use allocations::Stats;
 use days::get_day;
-use solution::{Part, SolvedValue};
+use observe::{ImageWriter, Logger, Observer, TextDump};
+use solution::{Context, Part, Solution, SolvedValue};
 
 /// Advent of Code 2024 runner implemented in Rust.
 ///
@@ -15,25 +34,193 @@ use solution::{Part, SolvedValue};
 /// If you're interested in how this works, the code is available here:
 /// <https://github.com/Snapstromegon/rustvent-of-code-24>
 #[derive(Parser, Debug)]
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "Every flag is an independent switch"
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Day to run - if not set, run all days
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     day: Option<usize>,
 
     /// Use example input instead of challenge input
-    #[arg(short, long, default_value_t = false)]
+    #[arg(short, long, default_value_t = false, global = true)]
     example: bool,
+
+    /// Override a day parameter, e.g. `--param connections=10` (can be repeated)
+    #[arg(short, long = "param", value_name = "NAME=VALUE", value_parser = parse_param, global = true)]
+    params: Vec<(String, String)>,
+
+    /// Solve with another implementation of the parts, e.g. `brute_force` (see `cross-check`)
+    #[arg(long = "impl", value_name = "NAME", default_value = solution::DEFAULT_IMPLEMENTATION, global = true)]
+    implementation: String,
+
+    /// Let days log the intermediate results that led to their answers
+    #[arg(long, default_value_t = false)]
+    explain: bool,
+
+    /// Check the inputs for assumptions the solutions rely on instead of solving them
+    #[arg(long, default_value_t = false)]
+    validate: bool,
+
+    /// Log progress (-v) or every step (-vv)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Animate what days do in the terminal
+    #[arg(long, default_value_t = false)]
+    visualize: bool,
+
+    /// Frames per second of the animation
+    #[arg(long, default_value_t = 10.0, value_parser = parse_fps, requires = "visualize")]
+    fps: f64,
+
+    /// Write everything days show as text to this file
+    #[arg(long, value_name = "FILE")]
+    frames: Option<PathBuf>,
+
+    /// Write the grids days show as images (and their graphs as Graphviz) into this directory
+    #[arg(long, value_name = "DIR")]
+    images: Option<PathBuf>,
+
+    /// Pixels per grid cell in images
+    #[arg(long, default_value_t = 4, requires = "images")]
+    image_scale: usize,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render the input of the selected day, e.g. as a Graphviz graph
+    Visualize {
+        /// File to write the visualisation to - if not set, print it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Compare all implementations of the parts on the input and generated ones
+    CrossCheck {
+        /// Size of the generated inputs
+        #[arg(long, default_value_t = 50)]
+        size: usize,
+        /// Number of generated inputs, with seeds starting at 0
+        #[arg(long, default_value_t = 20)]
+        seeds: u64,
+    },
+    /// Feed mutated inputs to the days and store the ones that crash them in fuzz/corpus
+    Fuzz {
+        /// Number of mutated inputs per day
+        #[arg(long, default_value_t = 10_000)]
+        iterations: usize,
+        /// Same seeds give the same mutations
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Time a part of the selected day on generated inputs of doubling size and fit the timings to
+    /// complexity classes
+    Scaling {
+        /// Part to time
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+        part: u8,
+        /// Size of the smallest input
+        #[arg(long, default_value_t = 100)]
+        from: usize,
+        /// Size the inputs don't grow beyond
+        #[arg(long, default_value_t = 6_400)]
+        to: usize,
+        /// Runs per size, of which the fastest counts
+        #[arg(long, default_value_t = 3)]
+        repeats: usize,
+        /// Same seeds give the same inputs
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Print a generated input for the selected day
+    Generate {
+        /// About how many items (lines, ranges, machines, ...) the input has
+        #[arg(long, default_value_t = 100)]
+        size: usize,
+        /// Same seeds give the same input
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
 }
 
-fn run_part(day: usize, part: Part, example: bool) -> Result<(SolvedValue, Duration), String> {
-    let input = utils::read_input(day, example, part.into());
+fn parse_param(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .ok_or_else(|| format!("Expected NAME=VALUE, got {s:?}"))
+}
+
+fn parse_fps(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(fps) if fps.is_finite() && fps > 0.0 => Ok(fps),
+        _ => Err(format!(
+            "Expected a positive number of frames per second, got {s:?}"
+        )),
+    }
+}
+
+fn build_context(
+    solution: &dyn Solution,
+    day: usize,
+    part: Part,
+    args: &Args,
+    observer: Option<&Arc<dyn Observer>>,
+) -> Result<Context, String> {
+    let mut ctx =
+        utils::read_context(solution, day, args.example, part.into()).map_err(|e| e.to_string())?;
+    for (name, value) in &args.params {
+        // When running all days, only apply parameters a day actually declares
+        if solution.parameters().iter().any(|param| param.name == name) {
+            ctx.set_param(name, value).map_err(|e| e.to_string())?;
+        }
+    }
+    ctx.set_explain(args.explain);
+    ctx.set_verbosity(args.verbose);
+    ctx.set_observer(observer.cloned());
+    Ok(ctx)
+}
+
+fn run_part(
+    day: usize,
+    part: Part,
+    args: &Args,
+    observer: Option<&Arc<dyn Observer>>,
+) -> Result<(SolvedValue, Duration, Option<Stats>), String> {
+    let input = utils::read_input(day, args.example, part.into());
     if let Some(solution) = get_day(day) {
         if let Some(input) = input {
+            let ctx = build_context(solution.as_ref(), day, part, args, observer)?;
+            if let Some(observer) = observer {
+                observer.begin(day, part);
+            }
+            ctx.info(|| {
+                let params: Vec<String> = ctx
+                    .params()
+                    .iter()
+                    .map(|(name, value)| format!("{name} = {value}"))
+                    .collect();
+                format!(
+                    "Solving {} input lines with parameters [{}]",
+                    input.lines().count(),
+                    params.join(", ")
+                )
+            });
             let start = std::time::Instant::now();
-            let result = solution.run(&input, part);
-            let duration = start.elapsed();
+            let (result, stats) = allocations::measure(|| {
+                solution.run_named(&input, &ctx, part, &args.implementation)
+            });
+            // Pacing an animation isn't part of solving
+            let paused = observer.map_or(Duration::ZERO, |observer| observer.paused());
+            let duration = start.elapsed().saturating_sub(paused);
+            ctx.info(|| format!("Solved in {duration:.1?}"));
+            if let Some(observer) = observer {
+                observer.end();
+            }
             if let Some(result) = result {
-                Ok((result, duration))
+                Ok((result, duration, stats))
             } else {
                 Err(format!("Day {day}.{part} not implemented"))
             }
@@ -47,30 +234,417 @@ fn run_part(day: usize, part: Part, example: bool) -> Result<(SolvedValue, Durat
     }
 }
 
-fn run_day(day: usize, example: bool) {
-    let res1 = run_part(day, Part::One, example);
-    let res2 = run_part(day, Part::Two, example);
+fn run_day(day: usize, args: &Args, observer: Option<&Arc<dyn Observer>>) {
+    let res1 = run_part(day, Part::One, args, observer);
+    let res2 = run_part(day, Part::Two, args, observer);
 
     print!("{day: >2} | ");
     match res1 {
-        Ok((result, duration)) => print!("{result} {duration: >7.1?} | "),
+        Ok((result, duration, stats)) => print!("{result} {duration: >7.1?}{} | ", costs(stats)),
         Err(e) => print!("{e: >24} | "),
     }
     match res2 {
-        Ok((result, duration)) => println!("{result} {duration: >7.1?}"),
+        Ok((result, duration, stats)) => println!("{result} {duration: >7.1?}{}", costs(stats)),
         Err(e) => println!("{e: >24}"),
     }
 }
 
+/// What a part allocated, if allocations are counted.
+fn costs(stats: Option<Stats>) -> String {
+    stats.map(|stats| format!(" [{stats}]")).unwrap_or_default()
+}
+
+/// Exits with a list of the available parameters if `day` doesn't know all given ones, or with the
+/// reason a value is invalid. When running all days, parameters of other days are skipped.
+fn check_params(day: usize, args: &Args) {
+    let Some(solution) = get_day(day) else {
+        return;
+    };
+    for part in [Part::One, Part::Two] {
+        if let Err(e) = utils::read_context(solution.as_ref(), day, args.example, part.into()) {
+            eprintln!("Invalid params file for day {day}: {e}");
+            std::process::exit(1);
+        }
+    }
+    for (name, value) in &args.params {
+        let Some(param) = solution
+            .parameters()
+            .iter()
+            .find(|param| param.name == name)
+        else {
+            if args.day.is_none() {
+                continue;
+            }
+            eprintln!("Day {day} has no parameter {name}, available are:");
+            for param in solution.parameters() {
+                eprintln!(
+                    "  {} (default {}): {}",
+                    param.name, param.default, param.description
+                );
+            }
+            std::process::exit(1);
+        };
+        if let Err(reason) = (param.check)(value) {
+            eprintln!("Invalid value {value:?} for parameter {name} of day {day}: {reason}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The day selected with `--day`, or all implemented days.
+fn selected_days(args: &Args) -> Vec<usize> {
+    args.day.map_or_else(
+        || (1..=25).filter(|&day| get_day(day).is_some()).collect(),
+        |day| vec![day],
+    )
+}
+
+/// Warns about every one of `parts` of the selected days that has no implementation with the name
+/// given by `--impl`, as those are solved with the default one. Exits with a list of the available
+/// implementations if none of them has it.
+fn check_implementation(parts: &[Part], args: &Args) {
+    if args.implementation == solution::DEFAULT_IMPLEMENTATION {
+        return;
+    }
+    let mut found = false;
+    let mut missing = Vec::new();
+    let mut available = Vec::new();
+    for day in selected_days(args) {
+        let Some(solution) = get_day(day) else {
+            continue;
+        };
+        let implementations = solution.implementations();
+        for &part in parts {
+            if implementations.iter().any(|implementation| {
+                implementation.part == part && implementation.name == args.implementation
+            }) {
+                found = true;
+            } else {
+                missing.push((day, part));
+            }
+        }
+        available.extend(implementations.iter().map(|implementation| {
+            format!(
+                "  {} (day {day} part {})",
+                implementation.name, implementation.part
+            )
+        }));
+    }
+    if !found {
+        eprintln!(
+            "No selected part has an implementation {}, available are:",
+            args.implementation
+        );
+        eprintln!("  {}", solution::DEFAULT_IMPLEMENTATION);
+        for implementation in available {
+            eprintln!("{implementation}");
+        }
+        std::process::exit(1);
+    }
+    for (day, part) in missing {
+        eprintln!(
+            "Day {day} part {part} has no implementation {}, solving it with the {} one",
+            args.implementation,
+            solution::DEFAULT_IMPLEMENTATION
+        );
+    }
+}
+
+fn visualize(day: usize, output: Option<&PathBuf>, args: &Args) -> Result<(), String> {
+    let solution = get_day(day).ok_or_else(|| format!("Day {day} not implemented"))?;
+    let input = utils::read_input(day, args.example, Part::One.into())
+        .ok_or_else(|| format!("No input for day {day}"))?;
+    let ctx = build_context(solution.as_ref(), day, Part::One, args, None)?;
+    let visualization = solution
+        .visualize(&input, &ctx)
+        .ok_or_else(|| format!("Day {day} has no visualisation"))?;
+    if let Some(path) = output {
+        std::fs::write(path, visualization.to_string())
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    } else {
+        print!("{visualization}");
+        Ok(())
+    }
+}
+
+/// Prints the assumptions the inputs of `day` break. Returns whether they hold all of them.
+fn validate(day: usize, args: &Args) -> Result<bool, String> {
+    let solution = get_day(day).ok_or_else(|| format!("Day {day} not implemented"))?;
+    let mut valid = true;
+    let mut previous_input = None;
+    for part in [Part::One, Part::Two] {
+        let input = utils::read_input(day, args.example, part.into())
+            .ok_or_else(|| format!("No input for day {day}.{part}"))?;
+        // Both parts usually share their input, there's no need to check it twice
+        if previous_input.as_ref() == Some(&input) {
+            continue;
+        }
+        let ctx = build_context(solution.as_ref(), day, part, args, None)?;
+        let violations = solution.validate(&input, &ctx);
+        if violations.is_empty() {
+            println!("{day: >2}.{part} | Input holds all assumptions");
+        }
+        for violation in &violations {
+            println!("{day: >2}.{part} | {violation}");
+        }
+        valid &= violations.is_empty();
+        previous_input = Some(input);
+    }
+    Ok(valid)
+}
+
+/// Compares the answers of all implementations of each part of `day` on the selected input and
+/// `seeds` generated ones. Prints every input they disagree on and returns whether they never did.
+fn cross_check(day: usize, size: usize, seeds: u64, args: &Args) -> Result<bool, String> {
+    let solution = get_day(day).ok_or_else(|| format!("Day {day} not implemented"))?;
+    let mut agreed = true;
+    for part in [Part::One, Part::Two] {
+        let names: Vec<&str> = solution
+            .implementations()
+            .iter()
+            .filter(|implementation| implementation.part == part)
+            .map(|implementation| implementation.name)
+            .collect();
+        if names.is_empty() {
+            continue;
+        }
+        let ctx = build_context(solution.as_ref(), day, part, args, None)?;
+        let mut inputs = Vec::new();
+        if let Some(input) = utils::read_input(day, args.example, part.into()) {
+            let kind = if args.example { "example" } else { "challenge" };
+            inputs.push((format!("the {kind} input"), input));
+        }
+        for seed in 0..seeds {
+            if let Some(input) = generate::generate(day, size, seed) {
+                let command = format!("generate --day {day} --size {size} --seed {seed}");
+                inputs.push((format!("the input of `{command}`"), input));
+            }
+        }
+        let mut disagreements = 0;
+        for (origin, input) in &inputs {
+            let answers = solution::answers(solution.as_ref(), input, &ctx, part);
+            if answers.iter().any(|(_, answer)| *answer != answers[0].1) {
+                disagreements += 1;
+                println!("{day: >2}.{part} | Implementations disagree on {origin}:");
+                for (name, answer) in &answers {
+                    let answer = answer.as_ref().map_or_else(
+                        || "no answer".to_string(),
+                        |a| a.to_string().trim().to_string(),
+                    );
+                    println!("     | {name: >12}: {answer}");
+                }
+            }
+        }
+        if disagreements == 0 {
+            println!(
+                "{day: >2}.{part} | {}, {} agree on {} inputs",
+                solution::DEFAULT_IMPLEMENTATION,
+                names.join(", "),
+                inputs.len()
+            );
+        }
+        agreed &= disagreements == 0;
+    }
+    Ok(agreed)
+}
+
+/// Fuzzes `day` with `iterations` mutated inputs and prints every crash found. Returns whether
+/// there was none.
+fn fuzz(day: usize, iterations: usize, seed: u64) -> bool {
+    let crashes = fuzz::run(
+        day,
+        &fuzz::seeds(day),
+        iterations,
+        &mut generate::Rng::new(seed),
+    );
+    println!(
+        "{day: >2} | {} crashes in {iterations} inputs",
+        crashes.len()
+    );
+    for crash in &crashes {
+        match fuzz::save(&fuzz::corpus_dir(day), crash) {
+            Ok(path) => println!("   | {crash}, saved to {}", path.display()),
+            Err(e) => println!("   | {crash}, not saved: {e}"),
+        }
+    }
+    crashes.is_empty()
+}
+
+/// Times a part of the selected day on generated inputs of doubling size and prints the timings
+/// with the complexity classes they fit best.
+fn scaling(args: &Args) -> Result<(), String> {
+    let Some(Command::Scaling {
+        part,
+        from,
+        to,
+        repeats,
+        seed,
+    }) = args.command
+    else {
+        unreachable!("Only called for the scaling command");
+    };
+    let day = args.day.ok_or("Select a day to time with --day")?;
+    let part = if part == 1 { Part::One } else { Part::Two };
+    check_implementation(&[part], args);
+    let sizes: Vec<usize> = std::iter::successors(Some(from.max(1)), |size| size.checked_mul(2))
+        .take_while(|&size| size <= to)
+        .collect();
+    let solution = get_day(day).ok_or_else(|| format!("Day {day} not implemented"))?;
+    if sizes.len() < 2 {
+        return Err("Timing needs at least two sizes, let --to be at least twice --from".into());
+    }
+    if generate::generate(day, sizes[0], seed).is_none() {
+        return Err(format!("Day {day} has no input generator"));
+    }
+    let ctx = build_context(solution.as_ref(), day, part, args, None)?;
+    println!(
+        "Day {day} part {part} ({}) on generated inputs",
+        args.implementation
+    );
+    let samples = scaling::measure(
+        &sizes,
+        repeats,
+        |size| generate::generate(day, size, seed).unwrap_or_default(),
+        |input| {
+            solution.run_named(input, &ctx, part, &args.implementation);
+        },
+        |sample| ctx.info(|| format!("Size {}: {:.1?}", sample.size, sample.time)),
+    );
+    let fits = scaling::fit(&samples);
+    println!("{}", scaling::chart(&samples, &fits[0]));
+    for fit in &fits {
+        match fit.error.exp() {
+            factor if factor < 100.0 => {
+                println!("{: >8} | off by a factor of {factor:.2}", fit.complexity);
+            }
+            _ => println!("{: >8} | off by a factor of 100 or more", fit.complexity),
+        }
+    }
+    println!("Best fit: O({})", fits[0].complexity);
+    Ok(())
+}
+
+/// Attaches every consumer of day events that was asked for.
+fn build_observer(args: &Args) -> Result<Option<Arc<dyn Observer>>, String> {
+    let mut observers: Vec<Box<dyn Observer>> = Vec::new();
+    if args.explain || args.verbose > 0 {
+        observers.push(Box::new(Logger::default()));
+    }
+    if args.visualize {
+        observers.push(Box::new(observe::Terminal::new(args.fps)));
+    }
+    if let Some(path) = &args.frames {
+        let dump = TextDump::create(path)
+            .map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+        observers.push(Box::new(dump));
+    }
+    if let Some(directory) = &args.images {
+        let writer = ImageWriter::create(directory, args.image_scale)
+            .map_err(|e| format!("Failed to create {}: {e}", directory.display()))?;
+        observers.push(Box::new(writer));
+    }
+    Ok(match observers.len() {
+        0 => None,
+        1 => observers.pop().map(Arc::from),
+        _ => Some(Arc::new(observers)),
+    })
+}
+
 fn main() {
     let args = Args::parse();
+    for day in selected_days(&args) {
+        check_params(day, &args);
+    }
+    match &args.command {
+        Some(Command::Visualize { output }) => {
+            let Some(day) = args.day else {
+                eprintln!("Select a day to visualize with --day");
+                std::process::exit(1);
+            };
+            if let Err(e) = visualize(day, output.as_ref(), &args) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::CrossCheck { size, seeds }) => {
+            let mut agreed = true;
+            for day in selected_days(&args) {
+                match cross_check(day, *size, *seeds, &args) {
+                    Ok(day_agreed) => agreed &= day_agreed,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        agreed = false;
+                    }
+                }
+            }
+            if !agreed {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Fuzz { iterations, seed }) => {
+            let mut survived = true;
+            for day in selected_days(&args) {
+                survived &= fuzz(day, *iterations, *seed);
+            }
+            if !survived {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Scaling { .. }) => {
+            if let Err(e) = scaling(&args) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Generate { size, seed }) => {
+            let Some(day) = args.day else {
+                eprintln!("Select a day to generate an input for with --day");
+                std::process::exit(1);
+            };
+            let Some(input) = generate::generate(day, *size, *seed) else {
+                eprintln!("Day {day} has no input generator");
+                std::process::exit(1);
+            };
+            print!("{input}");
+            return;
+        }
+        None => {}
+    }
+    if args.validate {
+        let mut valid = true;
+        for day in selected_days(&args) {
+            match validate(day, &args) {
+                Ok(day_valid) => valid &= day_valid,
+                Err(e) => {
+                    eprintln!("{e}");
+                    valid = false;
+                }
+            }
+        }
+        if !valid {
+            std::process::exit(1);
+        }
+        return;
+    }
+    let observer = match build_observer(&args) {
+        Ok(observer) => observer,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    check_implementation(&[Part::One, Part::Two], &args);
     if let Some(day) = args.day {
-        run_day(day, args.example);
+        run_day(day, &args, observer.as_ref());
     } else {
         let start = std::time::Instant::now();
         for day in 1..=25 {
             if get_day(day).is_some() {
-                run_day(day, args.example);
+                run_day(day, &args, observer.as_ref());
             }
         }
         let duration = start.elapsed();
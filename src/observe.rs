@@ -0,0 +1,381 @@
+use std::{
+    fmt::{Debug, Display},
+    fs::File,
+    io::{BufWriter, Stdout, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use crate::{animation::Frame, solution::Part, visualize::DotGraph};
+
+/// How detailed a log line is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Key facts that led to the answer, shown with `--explain`.
+    Explain,
+    /// Progress of the run, shown with `-v`.
+    Info,
+    /// Step by step details, shown with `-vv`.
+    Debug,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Explain => write!(f, "explain"),
+            Level::Info => write!(f, "info"),
+            Level::Debug => write!(f, "debug"),
+        }
+    }
+}
+
+/// Something a day wants to show about how it's solving a part.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// Snapshot of a grid, e.g. one step of a simulation.
+    Grid(Frame),
+    /// A connection between two nodes of a graph.
+    Edge { from: String, to: String },
+    /// A line of text.
+    Log { level: Level, message: String },
+}
+
+/// Consumer of the events days emit through [`crate::solution::Context::emit`].
+pub trait Observer: Debug + Send + Sync {
+    /// Called before `part` of `day` starts solving.
+    fn begin(&self, _day: usize, _part: Part) {}
+
+    fn observe(&self, event: Event);
+
+    /// Time spent waiting since [`Observer::begin`] to pace the output, which isn't part of the
+    /// time it took to solve the part.
+    fn paused(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Called after the part is solved.
+    fn end(&self) {}
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Passes every event on to all observers.
+impl Observer for Vec<Box<dyn Observer>> {
+    fn begin(&self, day: usize, part: Part) {
+        for observer in self {
+            observer.begin(day, part);
+        }
+    }
+
+    fn observe(&self, event: Event) {
+        for observer in self {
+            observer.observe(event.clone());
+        }
+    }
+
+    fn paused(&self) -> Duration {
+        self.iter().map(|observer| observer.paused()).sum()
+    }
+
+    fn end(&self) {
+        for observer in self {
+            observer.end();
+        }
+    }
+}
+
+/// Plays grids as an animation in the terminal and prints graph edges. Log lines are left to the
+/// [`Logger`].
+#[derive(Debug)]
+pub struct Terminal<W = Stdout> {
+    output: Mutex<W>,
+    delay: Duration,
+    /// Waits between grids
+    sleep: fn(Duration),
+    /// Lines of the last grid, which the next one is drawn over
+    previous_height: Mutex<usize>,
+    /// Time waited between the grids of the current part
+    paused: Mutex<Duration>,
+}
+
+impl Terminal {
+    /// Shows `fps` grids per second.
+    #[must_use]
+    pub fn new(fps: f64) -> Self {
+        Self::with_output(
+            std::io::stdout(),
+            Duration::from_secs_f64(1.0 / fps),
+            std::thread::sleep,
+        )
+    }
+}
+
+impl<W: Write> Terminal<W> {
+    fn with_output(output: W, delay: Duration, sleep: fn(Duration)) -> Self {
+        Self {
+            output: Mutex::new(output),
+            delay,
+            sleep,
+            previous_height: Mutex::new(0),
+            paused: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    fn write(&self, event: &Event) -> std::io::Result<()> {
+        let mut previous_height = lock(&self.previous_height);
+        let mut output = lock(&self.output);
+        match event {
+            Event::Grid(frame) => {
+                if *previous_height > 0 {
+                    // Move the cursor back to the start of the previous grid to draw over it
+                    write!(output, "\x1b[{previous_height}F")?;
+                }
+                write!(output, "{}", frame.ansi())?;
+                *previous_height = frame.height();
+            }
+            Event::Edge { from, to } => {
+                writeln!(output, "{from} -> {to}")?;
+                *previous_height = 0;
+            }
+            Event::Log { .. } => return Ok(()),
+        }
+        output.flush()
+    }
+}
+
+impl<W: Write + Send + Debug> Observer for Terminal<W> {
+    fn begin(&self, _day: usize, _part: Part) {
+        *lock(&self.paused) = Duration::ZERO;
+    }
+
+    fn observe(&self, event: Event) {
+        if let Err(e) = self.write(&event) {
+            eprintln!("Failed to show event: {e}");
+        }
+        if matches!(event, Event::Grid(_)) {
+            (self.sleep)(self.delay);
+            *lock(&self.paused) += self.delay;
+        }
+    }
+
+    fn paused(&self) -> Duration {
+        *lock(&self.paused)
+    }
+
+    fn end(&self) {
+        // Keep the last grid, the next part starts below it
+        *lock(&self.previous_height) = 0;
+    }
+}
+
+/// Prints log lines to stderr, tagged with the part they belong to.
+#[derive(Debug, Default)]
+pub struct Logger {
+    part: Mutex<Option<(usize, Part)>>,
+}
+
+impl Observer for Logger {
+    fn begin(&self, day: usize, part: Part) {
+        *lock(&self.part) = Some((day, part));
+    }
+
+    fn observe(&self, event: Event) {
+        if let Event::Log { level, message } = event {
+            match *lock(&self.part) {
+                Some((day, part)) => eprintln!("{day:02}.{part} [{level}] {message}"),
+                None => eprintln!("[{level}] {message}"),
+            }
+        }
+    }
+
+    fn end(&self) {
+        *lock(&self.part) = None;
+    }
+}
+
+/// Writes all events as plain text into one file.
+#[derive(Debug)]
+pub struct TextDump {
+    file: Mutex<BufWriter<File>>,
+}
+
+impl TextDump {
+    /// # Errors
+    /// If the file can't be created.
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            file: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    fn write(&self, text: &str) {
+        let mut file = lock(&self.file);
+        if let Err(e) = writeln!(file, "{text}") {
+            eprintln!("Failed to write event: {e}");
+        }
+    }
+}
+
+impl Observer for TextDump {
+    fn begin(&self, day: usize, part: Part) {
+        self.write(&format!("== Day {day}, part {part} =="));
+    }
+
+    fn observe(&self, event: Event) {
+        match event {
+            Event::Grid(frame) => self.write(&frame.plain()),
+            Event::Edge { from, to } => self.write(&format!("{from} -> {to}")),
+            Event::Log { level, message } => self.write(&format!("[{level}] {message}")),
+        }
+    }
+
+    fn end(&self) {
+        if let Err(e) = lock(&self.file).flush() {
+            eprintln!("Failed to write events: {e}");
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ImageState {
+    /// Start of the file names for the current part, e.g. `07-2`
+    prefix: String,
+    frames: usize,
+    edges: Vec<(String, String)>,
+}
+
+/// Writes every grid as a numbered PPM image and the edges of a part as one Graphviz graph.
+#[derive(Debug)]
+pub struct ImageWriter {
+    directory: PathBuf,
+    /// Pixels per grid cell
+    scale: usize,
+    state: Mutex<ImageState>,
+}
+
+impl ImageWriter {
+    /// # Errors
+    /// If the directory can't be created.
+    pub fn create(directory: &Path, scale: usize) -> std::io::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            scale,
+            state: Mutex::new(ImageState::default()),
+        })
+    }
+
+    fn write(&self, name: &str, content: &[u8]) {
+        let path = self.directory.join(name);
+        if let Err(e) = std::fs::write(&path, content) {
+            eprintln!("Failed to write {}: {e}", path.display());
+        }
+    }
+}
+
+impl Observer for ImageWriter {
+    fn begin(&self, day: usize, part: Part) {
+        *lock(&self.state) = ImageState {
+            prefix: format!("{day:02}-{part}"),
+            ..ImageState::default()
+        };
+    }
+
+    fn observe(&self, event: Event) {
+        let mut state = lock(&self.state);
+        match event {
+            Event::Grid(frame) => {
+                state.frames += 1;
+                let name = format!("{}-{:05}.ppm", state.prefix, state.frames);
+                drop(state);
+                self.write(&name, &frame.ppm(self.scale));
+            }
+            Event::Edge { from, to } => state.edges.push((from, to)),
+            Event::Log { .. } => {}
+        }
+    }
+
+    fn end(&self) {
+        let state = std::mem::take(&mut *lock(&self.state));
+        if !state.edges.is_empty() {
+            let mut dot = DotGraph::new(&state.prefix);
+            for (from, to) in &state.edges {
+                dot.edge(from, to, &[]);
+            }
+            self.write(
+                &format!("{}.dot", state.prefix),
+                dot.finish().to_string().as_bytes(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solution::Context;
+    use std::sync::Arc;
+
+    #[derive(Debug, Default)]
+    struct Collector(Mutex<Vec<Event>>);
+
+    impl Observer for Collector {
+        fn observe(&self, event: Event) {
+            lock(&self.0).push(event);
+        }
+    }
+
+    #[test]
+    fn test_logs_are_filtered() {
+        let collector = Arc::new(Collector::default());
+        let mut ctx = Context::default();
+        ctx.explain(|| panic!("Nothing is built without an observer"));
+        ctx.set_observer(Some(collector.clone()));
+        ctx.set_verbosity(1);
+        ctx.explain(|| panic!("Explaining is off"));
+        ctx.info(|| "info".to_string());
+        ctx.debug(|| panic!("Verbosity is too low"));
+        ctx.set_explain(true);
+        ctx.explain(|| "explain".to_string());
+        assert_eq!(
+            *lock(&collector.0),
+            vec![
+                Event::Log {
+                    level: Level::Info,
+                    message: "info".to_string()
+                },
+                Event::Log {
+                    level: Level::Explain,
+                    message: "explain".to_string()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pacing_is_tracked() {
+        let terminal = Terminal::with_output(Vec::new(), Duration::from_millis(20), |_| {});
+        terminal.begin(7, Part::Two);
+        terminal.observe(Event::Grid(Frame::new("Step 1")));
+        terminal.observe(Event::Grid(Frame::new("Step 2")));
+        terminal.observe(Event::Log {
+            level: Level::Info,
+            message: "Not shown".to_string(),
+        });
+        assert_eq!(terminal.paused(), Duration::from_millis(40));
+        assert_eq!(
+            String::from_utf8(lock(&terminal.output).clone()).unwrap(),
+            "\x1b[2KStep 1\n\x1b[1F\x1b[2KStep 2\n"
+        );
+        let observers: Vec<Box<dyn Observer>> =
+            vec![Box::new(terminal), Box::new(Collector::default())];
+        assert_eq!(observers.paused(), Duration::from_millis(40));
+        observers.begin(7, Part::Two);
+        assert_eq!(observers.paused(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,228 @@
+use std::{fmt::Display, ops::RangeInclusive, str::FromStr};
+
+/// Line and column of a byte offset, both starting at 1. Columns count characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Input that couldn't be parsed, pointing at the bytes of the text that are wrong.
+///
+/// Parsers only know the text they were given, so the offset is relative to that text. Whoever
+/// handed them a slice of a larger text moves the error there with [`ParseError::within`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    offset: usize,
+    len: usize,
+    message: String,
+}
+
+impl ParseError {
+    #[must_use]
+    pub fn new(offset: usize, len: usize, message: impl Into<String>) -> Self {
+        Self {
+            offset,
+            len,
+            message: message.into(),
+        }
+    }
+
+    /// Error about `fragment`, which has to be a slice of `text`.
+    #[must_use]
+    pub fn at(text: &str, fragment: &str, message: impl Into<String>) -> Self {
+        Self::new(offset_of(text, fragment), fragment.len(), message)
+    }
+
+    /// Error right after the end of `text`, e.g. for missing parts.
+    #[must_use]
+    pub fn at_end(text: &str, message: impl Into<String>) -> Self {
+        Self::new(text.len(), 0, message)
+    }
+
+    /// Moves an error from `fragment` to the `text` it was sliced from.
+    #[must_use]
+    pub fn within(mut self, text: &str, fragment: &str) -> Self {
+        self.offset += offset_of(text, fragment);
+        self
+    }
+
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Where the error is in `text`, which has to be the text the offset refers to.
+    #[must_use]
+    pub fn location(&self, text: &str) -> Location {
+        let before = &text[..self.offset.min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Location {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    /// Renders the error with the line it is in and carets below the wrong part.
+    #[must_use]
+    pub fn render(&self, text: &str) -> String {
+        let Location { line, column } = self.location(text);
+        let line_text = text.lines().nth(line - 1).unwrap_or("");
+        let start = line_text
+            .char_indices()
+            .nth(column - 1)
+            .map_or(line_text.len(), |(i, _)| i);
+        let end = (start + self.len).min(line_text.len());
+        let carets = line_text
+            .get(start..end)
+            .map_or(1, |wrong| wrong.chars().count().max(1));
+        let number = line.to_string();
+        let gutter = " ".repeat(number.len());
+        format!(
+            "error: {}\n{gutter}--> line {line}, column {column}\n{gutter} |\n{number} | {line_text}\n{gutter} | {}{}",
+            self.message,
+            " ".repeat(column - 1),
+            "^".repeat(carets)
+        )
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Byte offset of `fragment` in `text`.
+///
+/// # Panics
+/// If `fragment` isn't a slice of `text`.
+#[must_use]
+pub fn offset_of(text: &str, fragment: &str) -> usize {
+    let offset = fragment
+        .as_ptr()
+        .addr()
+        .checked_sub(text.as_ptr().addr())
+        .filter(|offset| offset + fragment.len() <= text.len());
+    offset.expect("Fragment is not part of the text")
+}
+
+/// Returns the parsed value or panics with the rendered error, for inputs a day can't work without.
+///
+/// # Panics
+/// If `result` is an error.
+pub fn or_report<T>(result: Result<T, ParseError>, text: &str) -> T {
+    result.unwrap_or_else(|e| reject(e.render(text)))
+}
+
+/// Gives up on an input a day can't solve, like one that parses but lacks the device to start at.
+///
+/// The message starts like the one of [`or_report`], so both can be told apart from bugs.
+///
+/// # Panics
+/// Always.
+pub fn reject(reason: impl Display) -> ! {
+    panic!("Invalid input\n{reason}")
+}
+
+/// Parses `fragment` of `text` as a number, ignoring surrounding whitespace.
+///
+/// # Errors
+/// If `fragment` isn't a valid `T`.
+pub fn number<T: FromStr>(text: &str, fragment: &str) -> Result<T, ParseError> {
+    let trimmed = fragment.trim();
+    trimmed.parse().map_err(|_| {
+        ParseError::at(
+            text,
+            trimmed,
+            format!("Expected a number, found {trimmed:?}"),
+        )
+    })
+}
+
+/// Splits `fragment` of `text` at the first `delimiter`.
+///
+/// # Errors
+/// If `fragment` doesn't contain `delimiter`.
+pub fn split_once<'a>(
+    text: &str,
+    fragment: &'a str,
+    delimiter: &str,
+) -> Result<(&'a str, &'a str), ParseError> {
+    fragment
+        .split_once(delimiter)
+        .ok_or_else(|| ParseError::at(text, fragment, format!("Expected {delimiter:?}")))
+}
+
+/// Parses a range like `11-22` in `fragment` of `text`.
+///
+/// # Errors
+/// If there are no two numbers separated by `-` or the range ends before it starts.
+pub fn range(text: &str, fragment: &str) -> Result<RangeInclusive<usize>, ParseError> {
+    let (start, end) = split_once(text, fragment.trim(), "-")?;
+    let (start, end) = (number(text, start)?, number(text, end)?);
+    if end < start {
+        return Err(ParseError::at(
+            text,
+            fragment.trim(),
+            "Range ends before it starts",
+        ));
+    }
+    Ok(start..=end)
+}
+
+/// Parses every non-empty line of `text` as a `T`.
+///
+/// # Errors
+/// The error of the first line that isn't a valid `T`, located in `text`.
+pub fn lines<T: FromStr<Err = ParseError>>(text: &str) -> Result<Vec<T>, ParseError> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.parse().map_err(|e: ParseError| e.within(text, line)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_and_render() {
+        let text = "1-2\n11-x1\n";
+        let line = text.lines().nth(1).unwrap();
+        let error = range(line, line).unwrap_err().within(text, line);
+        assert_eq!(error.offset(), 7);
+        assert_eq!(error.location(text), Location { line: 2, column: 4 });
+        assert_eq!(
+            error.render(text),
+            "error: Expected a number, found \"x1\"\n --> line 2, column 4\n  |\n2 | 11-x1\n  |    ^^"
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        #[derive(Debug, PartialEq)]
+        struct Value(u8);
+        impl FromStr for Value {
+            type Err = ParseError;
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                number(s, s).map(Value)
+            }
+        }
+        assert_eq!(lines("1\n\n2\n"), Ok(vec![Value(1), Value(2)]));
+        let error = lines::<Value>("1\n300\n").unwrap_err();
+        assert_eq!(error.location("1\n300\n"), Location { line: 2, column: 1 });
+        assert!(range("5-3", "5-3").is_err());
+        assert_eq!(
+            split_once("a b", "a b", ":"),
+            Err(ParseError::new(0, 3, "Expected \":\""))
+        );
+    }
+}
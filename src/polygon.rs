@@ -0,0 +1,487 @@
+use std::fmt::Display;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolygonError {
+    TooFewVertices(usize),
+    RepeatedVertex(Point),
+    DiagonalEdge { from: Point, to: Point },
+    SelfIntersecting { first: usize, second: usize },
+}
+
+impl Display for PolygonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolygonError::TooFewVertices(count) => {
+                write!(f, "A polygon needs at least 4 vertices, got {count}")
+            }
+            PolygonError::RepeatedVertex(point) => {
+                write!(f, "Vertex {},{} is repeated", point.x, point.y)
+            }
+            PolygonError::DiagonalEdge { from, to } => write!(
+                f,
+                "Edge from {},{} to {},{} is not axis aligned",
+                from.x, from.y, to.x, to.y
+            ),
+            PolygonError::SelfIntersecting { first, second } => {
+                write!(f, "Edges {first} and {second} intersect")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolygonError {}
+
+/// Bounding box of an axis aligned edge, which is the edge itself.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    min: Point,
+    max: Point,
+}
+
+impl Segment {
+    fn new(a: Point, b: Point) -> Self {
+        Segment {
+            min: Point {
+                x: a.x.min(b.x),
+                y: a.y.min(b.y),
+            },
+            max: Point {
+                x: a.x.max(b.x),
+                y: a.y.max(b.y),
+            },
+        }
+    }
+
+    fn is_vertical(&self) -> bool {
+        self.min.x == self.max.x
+    }
+
+    fn contains(&self, point: Point) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+    }
+
+    /// Number of points two edges share, capped at two.
+    fn shared_points(&self, other: &Segment) -> usize {
+        let (min_x, max_x) = (self.min.x.max(other.min.x), self.max.x.min(other.max.x));
+        let (min_y, max_y) = (self.min.y.max(other.min.y), self.max.y.min(other.max.y));
+        if min_x > max_x || min_y > max_y {
+            0
+        } else if min_x == max_x && min_y == max_y {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+/// A simple polygon whose edges are all horizontal or vertical.
+///
+/// Vertices are the centers of tiles, so a tile belongs to the polygon if its center lies inside or on
+/// the boundary.
+#[derive(Debug, Clone)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+
+impl Polygon {
+    /// Creates a polygon from its vertices in order, the last one being connected to the first.
+    ///
+    /// # Errors
+    /// If there are fewer than 4 vertices, a vertex is repeated right away, an edge is diagonal or
+    /// edges intersect anywhere except at their shared vertex.
+    pub fn new(vertices: Vec<Point>) -> Result<Self, PolygonError> {
+        if vertices.len() < 4 {
+            return Err(PolygonError::TooFewVertices(vertices.len()));
+        }
+        let polygon = Polygon { vertices };
+        for (from, to) in polygon.edges() {
+            if from == to {
+                return Err(PolygonError::RepeatedVertex(from));
+            }
+            if from.x != to.x && from.y != to.y {
+                return Err(PolygonError::DiagonalEdge { from, to });
+            }
+        }
+        let segments: Vec<Segment> = polygon.segments().collect();
+        let count = segments.len();
+        for (first, a) in segments.iter().enumerate() {
+            for (second, b) in segments.iter().enumerate().skip(first + 1) {
+                let adjacent = second == first + 1 || (first == 0 && second == count - 1);
+                let allowed = usize::from(adjacent);
+                if a.shared_points(b) > allowed {
+                    return Err(PolygonError::SelfIntersecting { first, second });
+                }
+            }
+        }
+        Ok(polygon)
+    }
+
+    #[must_use]
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    /// All edges as pairs of consecutive vertices, including the closing one.
+    pub fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        self.vertices
+            .iter()
+            .copied()
+            .zip(self.vertices.iter().copied().cycle().skip(1))
+    }
+
+    fn segments(&self) -> impl Iterator<Item = Segment> + '_ {
+        self.edges().map(|(a, b)| Segment::new(a, b))
+    }
+
+    /// Checks if a point lies inside or on the boundary of the polygon.
+    ///
+    /// Casts a ray to the right and counts the vertical edges it crosses. Edges are treated as
+    /// half-open in y, so a ray passing exactly through a vertex is counted once.
+    #[must_use]
+    pub fn contains(&self, point: Point) -> bool {
+        let mut inside = false;
+        for segment in self.segments() {
+            if segment.contains(point) {
+                return true;
+            }
+            if segment.is_vertical()
+                && segment.min.x > point.x
+                && (segment.min.y..segment.max.y).contains(&point.y)
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Area enclosed by the edges (through the tile centers) using the shoelace formula.
+    #[must_use]
+    pub fn area(&self) -> u128 {
+        let twice_area: i128 = self
+            .edges()
+            .map(|(a, b)| a.x as i128 * b.y as i128 - b.x as i128 * a.y as i128)
+            .sum();
+        twice_area.unsigned_abs() / 2
+    }
+
+    /// Number of tiles on the boundary or inside, derived from the area with Pick's theorem.
+    #[must_use]
+    pub fn tile_count(&self) -> u128 {
+        let boundary: u128 = self
+            .edges()
+            .map(|(a, b)| (a.x.abs_diff(b.x) + a.y.abs_diff(b.y)) as u128)
+            .sum();
+        self.area() + boundary / 2 + 1
+    }
+}
+
+/// Maps coordinates to their index in the sorted list of all distinct coordinates that occur.
+#[derive(Debug)]
+pub struct Compressor {
+    xs: Vec<usize>,
+    ys: Vec<usize>,
+}
+
+impl Compressor {
+    #[must_use]
+    pub fn new(points: &[Point]) -> Self {
+        let mut compressed_x = Vec::new();
+        let mut compressed_y = Vec::new();
+        for &Point { x, y } in points {
+            compressed_x.push(x);
+            compressed_y.push(y);
+        }
+        compressed_x.sort_unstable();
+        compressed_x.dedup();
+        compressed_y.sort_unstable();
+        compressed_y.dedup();
+        Compressor {
+            xs: compressed_x,
+            ys: compressed_y,
+        }
+    }
+
+    #[must_use]
+    pub fn xs(&self) -> &[usize] {
+        &self.xs
+    }
+
+    #[must_use]
+    pub fn ys(&self) -> &[usize] {
+        &self.ys
+    }
+
+    #[must_use]
+    pub fn compress_x(&self, x: usize) -> Option<usize> {
+        self.xs.binary_search(&x).ok()
+    }
+
+    #[must_use]
+    pub fn compress_y(&self, y: usize) -> Option<usize> {
+        self.ys.binary_search(&y).ok()
+    }
+
+    #[must_use]
+    pub fn compress(&self, pos: &Point) -> Option<Point> {
+        Some(Point {
+            x: self.compress_x(pos.x)?,
+            y: self.compress_y(pos.y)?,
+        })
+    }
+
+    #[must_use]
+    pub fn decompress_x(&self, x: usize) -> Option<usize> {
+        self.xs.get(x).copied()
+    }
+
+    #[must_use]
+    pub fn decompress_y(&self, y: usize) -> Option<usize> {
+        self.ys.get(y).copied()
+    }
+
+    #[must_use]
+    pub fn decompress(&self, pos: &Point) -> Option<Point> {
+        Some(Point {
+            x: self.decompress_x(pos.x)?,
+            y: self.decompress_y(pos.y)?,
+        })
+    }
+}
+
+/// Index of the compressed cell a coordinate falls into.
+///
+/// Even cells are the known coordinates themselves, odd cells are the gaps between two neighbouring
+/// coordinates.
+fn cell_index(coords: &[usize], value: usize) -> Option<usize> {
+    match coords.binary_search(&value) {
+        Ok(i) => Some(2 * i),
+        Err(i) if i == 0 || i == coords.len() => None,
+        Err(i) => Some(2 * i - 1),
+    }
+}
+
+/// Compressed grid of the polygon's tiles that answers whether a rectangle lies fully inside the
+/// polygon in constant time.
+///
+/// Every row and column of the grid is either a vertex coordinate or the gap between two of them.
+/// Within such a cell all tiles are either inside or outside, so it is enough to classify one point
+/// per cell. A 2D prefix sum over the outside cells then gives the number of outside cells in any
+/// rectangle.
+#[derive(Debug)]
+pub struct InsideGrid {
+    compressor: Compressor,
+    outside_prefix: Vec<Vec<usize>>,
+}
+
+impl InsideGrid {
+    #[must_use]
+    pub fn new(polygon: &Polygon) -> Self {
+        let compressor = Compressor::new(polygon.vertices());
+        let width = 2 * compressor.xs.len() - 1;
+        let height = 2 * compressor.ys.len() - 1;
+        let mut boundary = vec![vec![false; width]; height];
+        let mut crossings = vec![vec![false; width]; height];
+        // Every vertex is part of the compressor, so none gets lost here
+        let compressed: Vec<Point> = polygon
+            .vertices()
+            .iter()
+            .filter_map(|vertex| compressor.compress(vertex))
+            .collect();
+        for (a, b) in compressed.iter().zip(compressed.iter().cycle().skip(1)) {
+            let segment = Segment::new(
+                Point {
+                    x: 2 * a.x,
+                    y: 2 * a.y,
+                },
+                Point {
+                    x: 2 * b.x,
+                    y: 2 * b.y,
+                },
+            );
+            for row in &mut boundary[segment.min.y..=segment.max.y] {
+                row[segment.min.x..=segment.max.x].fill(true);
+            }
+            if segment.is_vertical() {
+                for row in &mut crossings[segment.min.y..segment.max.y] {
+                    row[segment.min.x] = !row[segment.min.x];
+                }
+            }
+        }
+
+        let is_gap_empty = |coords: &[usize], cell: usize| {
+            cell % 2 == 1 && coords[cell / 2 + 1] - coords[cell / 2] == 1
+        };
+        let mut outside_prefix = vec![vec![0; width + 1]; height + 1];
+        for row in 0..height {
+            let mut inside = false;
+            for col in 0..width {
+                let is_outside = !boundary[row][col]
+                    && !inside
+                    && !is_gap_empty(&compressor.xs, col)
+                    && !is_gap_empty(&compressor.ys, row);
+                outside_prefix[row + 1][col + 1] = usize::from(is_outside)
+                    + outside_prefix[row][col + 1]
+                    + outside_prefix[row + 1][col]
+                    - outside_prefix[row][col];
+                if crossings[row][col] {
+                    inside = !inside;
+                }
+            }
+        }
+
+        InsideGrid {
+            compressor,
+            outside_prefix,
+        }
+    }
+
+    #[must_use]
+    pub fn compressor(&self) -> &Compressor {
+        &self.compressor
+    }
+
+    /// Checks if every tile of the rectangle spanned by two corners is inside the polygon.
+    #[must_use]
+    pub fn contains_rectangle(&self, a: Point, b: Point) -> bool {
+        let cells = (
+            cell_index(&self.compressor.xs, a.x.min(b.x)),
+            cell_index(&self.compressor.xs, a.x.max(b.x)),
+            cell_index(&self.compressor.ys, a.y.min(b.y)),
+            cell_index(&self.compressor.ys, a.y.max(b.y)),
+        );
+        let (Some(x1), Some(x2), Some(y1), Some(y2)) = cells else {
+            return false;
+        };
+        let prefix = &self.outside_prefix;
+        prefix[y2 + 1][x2 + 1] + prefix[y1][x1] - prefix[y1][x2 + 1] - prefix[y2 + 1][x1] == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::property::{check, equal};
+
+    fn points(coords: &[(usize, usize)]) -> Vec<Point> {
+        coords.iter().map(|&(x, y)| Point { x, y }).collect()
+    }
+
+    /// A U shape with a one tile wide gap at the top.
+    fn u_shape() -> Polygon {
+        Polygon::new(points(&[
+            (0, 0),
+            (2, 0),
+            (2, 5),
+            (4, 5),
+            (4, 0),
+            (7, 0),
+            (7, 8),
+            (0, 8),
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validation() {
+        assert_eq!(
+            Polygon::new(points(&[(0, 0), (1, 0), (1, 1)])).unwrap_err(),
+            PolygonError::TooFewVertices(3)
+        );
+        assert!(matches!(
+            Polygon::new(points(&[(0, 0), (2, 0), (2, 2), (1, 3)])),
+            Err(PolygonError::DiagonalEdge { .. })
+        ));
+        // The edge from 2,4 to 2,0 crosses the first one
+        assert_eq!(
+            Polygon::new(points(&[(0, 2), (4, 2), (4, 4), (2, 4), (2, 0), (0, 0)])).unwrap_err(),
+            PolygonError::SelfIntersecting {
+                first: 0,
+                second: 3
+            }
+        );
+        // Going back along the previous edge
+        assert!(matches!(
+            Polygon::new(points(&[(0, 0), (4, 0), (2, 0), (2, 2), (0, 2)])),
+            Err(PolygonError::SelfIntersecting { .. })
+        ));
+        assert!(Polygon::new(u_shape().vertices().to_vec()).is_ok());
+    }
+
+    #[test]
+    fn test_contains_and_area() {
+        let polygon = u_shape();
+        let tiles = (0..10)
+            .flat_map(|y| (0..10).map(move |x| Point { x, y }))
+            .filter(|&p| polygon.contains(p))
+            .count();
+        assert_eq!(tiles as u128, polygon.tile_count());
+        assert_eq!(polygon.area(), 7 * 8 - 2 * 5);
+        assert!(!polygon.contains(Point { x: 3, y: 0 }));
+        assert!(!polygon.contains(Point { x: 3, y: 4 }));
+        assert!(polygon.contains(Point { x: 3, y: 5 }));
+        assert!(polygon.contains(Point { x: 3, y: 6 }));
+    }
+
+    #[test]
+    fn test_contains_rectangle_matches_tiles() {
+        let polygon = u_shape();
+        let grid = InsideGrid::new(&polygon);
+        let coords: Vec<Point> = (0..9)
+            .flat_map(|y| (0..9).map(move |x| Point { x, y }))
+            .collect();
+        for &a in &coords {
+            for &b in &coords {
+                let expected = (a.x.min(b.x)..=a.x.max(b.x)).all(|x| {
+                    (a.y.min(b.y)..=a.y.max(b.y)).all(|y| polygon.contains(Point { x, y }))
+                });
+                assert_eq!(grid.contains_rectangle(a, b), expected, "{a:?} {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_compressor_round_trip() {
+        let corners = points(&[(100, 7), (3, 7), (100, 50_000)]);
+        let compressor = Compressor::new(&corners);
+        assert_eq!(compressor.xs(), &[3, 100]);
+        for corner in &corners {
+            let compressed = compressor.compress(corner).unwrap();
+            assert_eq!(compressor.decompress(&compressed), Some(*corner));
+        }
+        assert_eq!(compressor.compress(&Point { x: 4, y: 7 }), None);
+    }
+
+    #[test]
+    fn test_compressor_round_trip_property() {
+        check(
+            |rng| {
+                (0..rng.range(1..=20))
+                    .map(|_| (rng.range(0..=1_000), rng.range(0..=1_000)))
+                    .collect::<Vec<_>>()
+            },
+            |coords| {
+                let compressor = Compressor::new(&points(coords));
+                for point in points(coords) {
+                    let compressed = compressor.compress(&point);
+                    equal(
+                        &compressed.and_then(|c| compressor.decompress(&c)),
+                        &Some(point),
+                    )?;
+                }
+                for (i, &x) in compressor.xs().iter().enumerate() {
+                    equal(&compressor.compress_x(x), &Some(i))?;
+                }
+                for (i, &y) in compressor.ys().iter().enumerate() {
+                    equal(&compressor.compress_y(y), &Some(i))?;
+                }
+                Ok(())
+            },
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Property tests: a property is checked on many random values and a failing value is shrunk to a
+//! minimal counterexample before it is reported.
+
+use std::fmt::Debug;
+
+use crate::generate::Rng;
+
+/// Number of random values every property is checked on.
+const CASES: usize = 256;
+
+/// Upper bound of shrinking steps, so a property that keeps failing on ever smaller values ends.
+const MAX_SHRINKS: usize = 10_000;
+
+/// Values that can be made smaller while they keep failing a property.
+pub trait Shrink: Sized {
+    /// Smaller variants of this value, the most promising (smallest) first.
+    fn shrink(&self) -> Vec<Self>;
+}
+
+macro_rules! shrink_unsigned {
+    ($($t:ty),*) => {$(
+        impl Shrink for $t {
+            /// 0, then ever closer to the value, like a binary search for the smallest failing one.
+            fn shrink(&self) -> Vec<Self> {
+                let mut smaller = vec![0];
+                let mut step = *self / 2;
+                while step > 0 {
+                    smaller.push(*self - step);
+                    step /= 2;
+                }
+                smaller.push(self.saturating_sub(1));
+                smaller.dedup();
+                smaller.retain(|n| n < self);
+                smaller
+            }
+        }
+    )*};
+}
+
+shrink_unsigned!(u8, u64, usize);
+
+impl Shrink for bool {
+    fn shrink(&self) -> Vec<Self> {
+        if *self { vec![false] } else { vec![] }
+    }
+}
+
+impl<T: Shrink + Clone> Shrink for Vec<T> {
+    /// Drops the second half, then single items, then shrinks the items one by one.
+    fn shrink(&self) -> Vec<Self> {
+        let mut smaller = Vec::new();
+        if self.len() > 1 {
+            smaller.push(self[..self.len() / 2].to_vec());
+        }
+        for i in 0..self.len() {
+            let mut without = self.clone();
+            without.remove(i);
+            smaller.push(without);
+        }
+        for (i, item) in self.iter().enumerate() {
+            for shrunk in item.shrink() {
+                let mut with = self.clone();
+                with[i] = shrunk;
+                smaller.push(with);
+            }
+        }
+        smaller
+    }
+}
+
+impl<A: Shrink + Clone, B: Shrink + Clone> Shrink for (A, B) {
+    fn shrink(&self) -> Vec<Self> {
+        let first = self.0.shrink().into_iter().map(|a| (a, self.1.clone()));
+        let second = self.1.shrink().into_iter().map(|b| (self.0.clone(), b));
+        first.chain(second).collect()
+    }
+}
+
+/// Checks `property` on values made by `generate`.
+///
+/// # Panics
+/// With the smallest value found that still fails the property and the property's message for it.
+pub fn check<T: Shrink + Clone + Debug>(
+    generate: impl Fn(&mut Rng) -> T,
+    property: impl Fn(&T) -> Result<(), String>,
+) {
+    let mut rng = Rng::new(0);
+    for case in 0..CASES {
+        let value = generate(&mut rng);
+        if let Err(message) = property(&value) {
+            let (value, message, shrinks) = shrink(value, message, &property);
+            panic!(
+                "Property failed on case {case}, shrunk in {shrinks} steps to\n{value:#?}\n{message}"
+            );
+        }
+    }
+}
+
+/// Replaces `value` by smaller ones as long as one of them fails the property as well.
+fn shrink<T: Shrink>(
+    mut value: T,
+    mut message: String,
+    property: impl Fn(&T) -> Result<(), String>,
+) -> (T, String, usize) {
+    let mut shrinks = 0;
+    'shrinking: while shrinks < MAX_SHRINKS {
+        for smaller in value.shrink() {
+            if let Err(smaller_message) = property(&smaller) {
+                value = smaller;
+                message = smaller_message;
+                shrinks += 1;
+                continue 'shrinking;
+            }
+        }
+        break;
+    }
+    (value, message, shrinks)
+}
+
+/// Fails with both values if they differ.
+///
+/// # Errors
+/// If `actual` isn't `expected`.
+pub fn equal<T: PartialEq + Debug>(actual: &T, expected: &T) -> Result<(), String> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!("Expected {expected:?}, got {actual:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shrinks_to_minimal_counterexample() {
+        let property = |numbers: &Vec<usize>| {
+            if numbers.iter().sum::<usize>() < 100 {
+                Ok(())
+            } else {
+                Err("Sum is too large".to_string())
+            }
+        };
+        let (value, message, _) = shrink(vec![7, 300, 12, 5], String::new(), property);
+        assert_eq!(value, vec![100]);
+        assert_eq!(message, "Sum is too large");
+    }
+
+    #[test]
+    fn test_check() {
+        check(
+            |rng| (rng.range(0..=10), rng.chance(1, 2)),
+            |&(n, _)| equal(&(n <= 10), &true),
+        );
+        let failure = std::panic::catch_unwind(|| {
+            check(|rng| rng.range(0..=1_000), |&n| equal(&(n < 500), &true));
+        })
+        .unwrap_err();
+        let message = failure.downcast_ref::<String>().unwrap();
+        assert!(message.contains("to\n500\n"), "{message}");
+    }
+}
@@ -0,0 +1,245 @@
+//! Empirical complexity: a day is timed on generated inputs of growing size and the timings are
+//! fitted to common complexity classes.
+
+use std::{
+    fmt::{Display, Write},
+    time::{Duration, Instant},
+};
+
+/// Width of the bars in [`chart`].
+const CHART_WIDTH: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Complexity {
+    Linear,
+    Linearithmic,
+    Quadratic,
+    Cubic,
+    Exponential,
+}
+
+impl Complexity {
+    pub const ALL: [Complexity; 5] = [
+        Complexity::Linear,
+        Complexity::Linearithmic,
+        Complexity::Quadratic,
+        Complexity::Cubic,
+        Complexity::Exponential,
+    ];
+
+    /// Natural logarithm of the growth function at `n`, so even 2^n for large `n` stays finite.
+    fn ln(self, n: f64) -> f64 {
+        let n = n.max(2.0);
+        match self {
+            Complexity::Linear => n.ln(),
+            Complexity::Linearithmic => n.ln() + n.log2().ln(),
+            Complexity::Quadratic => 2.0 * n.ln(),
+            Complexity::Cubic => 3.0 * n.ln(),
+            Complexity::Exponential => n * std::f64::consts::LN_2,
+        }
+    }
+}
+
+impl Display for Complexity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Complexity::Linear => "n",
+            Complexity::Linearithmic => "n log n",
+            Complexity::Quadratic => "n²",
+            Complexity::Cubic => "n³",
+            Complexity::Exponential => "2ⁿ",
+        };
+        f.pad(name)
+    }
+}
+
+/// Fastest time of a day on an input of `size` items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub size: usize,
+    pub time: Duration,
+}
+
+/// The timings described as `time = factor * f(size)` for the growth function `f` of a complexity.
+#[derive(Debug, Clone, Copy)]
+pub struct Fit {
+    pub complexity: Complexity,
+    /// Natural logarithm of the factor in seconds
+    ln_factor: f64,
+    /// Root mean square of the natural logarithm of measured by predicted time, i.e. 0.1 means the
+    /// prediction is off by about 10%
+    pub error: f64,
+}
+
+impl Fit {
+    /// Time the fit predicts for an input of `size` items.
+    #[must_use]
+    pub fn predict(&self, size: usize) -> Duration {
+        let seconds = (self.ln_factor + self.complexity.ln(float(size))).exp();
+        Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX)
+    }
+}
+
+/// `n` as float.
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "Sizes and sample counts are far below 2^52, where floats stop being exact"
+)]
+fn float(n: usize) -> f64 {
+    n as f64
+}
+
+/// Natural logarithm of a time in seconds, with a floor for times too short to measure.
+fn ln_seconds(time: Duration) -> f64 {
+    time.as_secs_f64().max(1e-9).ln()
+}
+
+/// Fits the samples to every complexity class, best fit first.
+///
+/// The fit happens on a logarithmic scale, so every sample counts the same no matter how long it
+/// took, and only the factor is fitted while the exponent is given by the class.
+///
+/// # Panics
+/// If `samples` is empty.
+#[must_use]
+pub fn fit(samples: &[Sample]) -> Vec<Fit> {
+    assert!(!samples.is_empty(), "Can't fit without samples");
+    let count = float(samples.len());
+    let mut fits: Vec<Fit> = Complexity::ALL
+        .iter()
+        .map(|&complexity| {
+            let residual =
+                |sample: &Sample| ln_seconds(sample.time) - complexity.ln(float(sample.size));
+            let ln_factor = samples.iter().map(residual).sum::<f64>() / count;
+            let error = (samples
+                .iter()
+                .map(|sample| (residual(sample) - ln_factor).powi(2))
+                .sum::<f64>()
+                / count)
+                .sqrt();
+            Fit {
+                complexity,
+                ln_factor,
+                error,
+            }
+        })
+        .collect();
+    fits.sort_by(|a, b| a.error.total_cmp(&b.error));
+    fits
+}
+
+/// Times `solve` on an input of every size made by `generate`, keeping the fastest of `repeats`
+/// runs. Generating the inputs isn't timed.
+pub fn measure(
+    sizes: &[usize],
+    repeats: usize,
+    generate: impl Fn(usize) -> String,
+    solve: impl Fn(&str),
+    mut progress: impl FnMut(&Sample),
+) -> Vec<Sample> {
+    sizes
+        .iter()
+        .map(|&size| {
+            let input = generate(size);
+            let time = (0..repeats.max(1))
+                .map(|_| {
+                    let start = Instant::now();
+                    solve(&input);
+                    start.elapsed()
+                })
+                .min()
+                .unwrap_or_default();
+            let sample = Sample { size, time };
+            progress(&sample);
+            sample
+        })
+        .collect()
+}
+
+/// One bar per sample, scaled to the slowest one, with `|` marking the time `fit` predicts.
+#[must_use]
+pub fn chart(samples: &[Sample], fit: &Fit) -> String {
+    let slowest = samples
+        .iter()
+        .map(|sample| sample.time.max(fit.predict(sample.size)))
+        .max()
+        .unwrap_or_default()
+        .as_secs_f64()
+        .max(1e-9);
+    let column = |time: Duration| {
+        let share = time.as_secs_f64() / slowest;
+        (0..=CHART_WIDTH)
+            .rev()
+            .find(|&column| float(column) <= (share * float(CHART_WIDTH)).round())
+            .unwrap_or(0)
+    };
+    let mut chart = String::new();
+    for sample in samples {
+        let (bar, marker) = (column(sample.time), column(fit.predict(sample.size)));
+        let row: String = (0..=CHART_WIDTH)
+            .map(|col| match col {
+                _ if col == marker => '|',
+                _ if col < bar => '█',
+                _ => ' ',
+            })
+            .collect();
+        let _ = writeln!(
+            chart,
+            "{: >8} | {: >9} | {}",
+            sample.size,
+            format!("{:.1?}", sample.time),
+            row.trim_end()
+        );
+    }
+    chart
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(f: impl Fn(f64) -> f64) -> Vec<Sample> {
+        [100, 200, 400, 800, 1_600, 3_200]
+            .into_iter()
+            .map(|size| Sample {
+                size,
+                time: Duration::from_secs_f64(f(float(size)) * 1e-9),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fit_finds_complexity() {
+        for (complexity, f) in [
+            (Complexity::Linear, (|n| 3.0 * n) as fn(f64) -> f64),
+            (Complexity::Linearithmic, |n| n * n.log2()),
+            (Complexity::Quadratic, |n| 0.5 * n * n),
+            (Complexity::Cubic, |n| n * n * n / 10.0),
+        ] {
+            let fits = fit(&samples(f));
+            assert_eq!(fits[0].complexity, complexity);
+            assert!(fits[0].error < 1e-3, "{fits:?}");
+        }
+        let fits = fit(&[10, 12, 14, 16, 18].map(|size| Sample {
+            size,
+            time: Duration::from_micros(1 << size),
+        }));
+        assert_eq!(fits[0].complexity, Complexity::Exponential);
+    }
+
+    #[test]
+    fn test_chart() {
+        let samples = samples(|n| n * n);
+        let best = fit(&samples)[0];
+        let chart = chart(&samples, &best);
+        assert_eq!(chart.lines().count(), samples.len());
+        assert!(
+            chart
+                .lines()
+                .last()
+                .unwrap()
+                .ends_with(&format!("{}|", "█".repeat(50)))
+        );
+        assert!(chart.starts_with("     100 |    10.0µs | |\n"), "{chart}");
+    }
+}
@@ -1,4 +1,13 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::Arc};
+
+use anyhow::bail;
+
+use crate::{
+    generate::Rng,
+    observe::{Event, Level, Observer},
+    parse::ParseError,
+    visualize::Visualization,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SolvedValue {
@@ -6,6 +15,20 @@ pub enum SolvedValue {
     String(String),
 }
 
+impl SolvedValue {
+    /// Wraps a result that might not fit into a `usize`, falling back to its decimal string.
+    #[must_use]
+    pub fn wide(value: u128) -> Self {
+        usize::try_from(value).map_or_else(|_| Self::String(value.to_string()), Self::Usize)
+    }
+
+    /// Same as [`SolvedValue::wide`] for results that might be negative.
+    #[must_use]
+    pub fn signed(value: i128) -> Self {
+        usize::try_from(value).map_or_else(|_| Self::String(value.to_string()), Self::Usize)
+    }
+}
+
 impl From<usize> for SolvedValue {
     fn from(value: usize) -> Self {
         Self::Usize(value)
@@ -33,23 +56,258 @@ impl Display for SolvedValue {
     }
 }
 
+/// A named input parameter a day can be tuned with, e.g. the number of connections in day 8.
+#[derive(Debug, Clone, Copy)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub default: &'static str,
+    pub description: &'static str,
+    /// Checks a value before it is set, usually [`parses_as`] the type the day reads it as
+    pub check: fn(&str) -> Result<(), String>,
+}
+
+/// Parameter check for values read as `T` with [`Context::param`].
+///
+/// # Errors
+/// Why `value` isn't a valid `T`.
+pub fn parses_as<T: FromStr<Err: Display>>(value: &str) -> Result<(), String> {
+    value.parse::<T>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Name the `part1` and `part2` methods of a solution are available under.
+pub const DEFAULT_IMPLEMENTATION: &str = "default";
+
+/// Another way to solve a part, e.g. a naive one to check a clever one against.
+#[derive(Debug, Clone, Copy)]
+pub struct Implementation {
+    pub name: &'static str,
+    pub part: Part,
+    pub solve: fn(&str, &Context) -> Option<SolvedValue>,
+}
+
+/// An assumption a solution relies on that an input doesn't hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// What the solution assumes, e.g. that a graph is acyclic
+    pub assumption: &'static str,
+    /// Where the input breaks the assumption
+    pub detail: String,
+}
+
+impl Violation {
+    #[must_use]
+    pub fn new(assumption: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            assumption,
+            detail: detail.into(),
+        }
+    }
+
+    /// The input couldn't even be parsed, so no other assumption could be checked.
+    #[must_use]
+    pub fn malformed(input: &str, error: &ParseError) -> Self {
+        Self::new("The input is well-formed", error.render(input))
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.assumption, self.detail)
+    }
+}
+
+/// Everything a solution gets to know about the current run besides the input itself.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    example: bool,
+    explain: bool,
+    verbosity: u8,
+    observer: Option<Arc<dyn Observer>>,
+    declared: &'static [Parameter],
+    params: HashMap<&'static str, String>,
+}
+
+impl Context {
+    /// Creates a context with all parameters of `solution` set to their defaults.
+    #[must_use]
+    pub fn new(solution: &dyn Solution, example: bool) -> Self {
+        Self {
+            example,
+            explain: false,
+            verbosity: 0,
+            observer: None,
+            declared: solution.parameters(),
+            params: solution
+                .parameters()
+                .iter()
+                .map(|param| (param.name, param.default.to_string()))
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_example(&self) -> bool {
+        self.example
+    }
+
+    /// Shows the facts behind the answer, i.e. logs of [`Level::Explain`].
+    pub fn set_explain(&mut self, explain: bool) {
+        self.explain = explain;
+    }
+
+    /// Shows logs of [`Level::Info`] from 1 and [`Level::Debug`] from 2 on.
+    pub fn set_verbosity(&mut self, verbosity: u8) {
+        self.verbosity = verbosity;
+    }
+
+    /// Whether log lines of `level` go anywhere, e.g. to skip work that only feeds the logs.
+    #[must_use]
+    pub fn logs(&self, level: Level) -> bool {
+        self.observer.is_some()
+            && match level {
+                Level::Explain => self.explain,
+                Level::Info => self.verbosity >= 1,
+                Level::Debug => self.verbosity >= 2,
+            }
+    }
+
+    /// Logs the line built by `message` if `level` is shown, otherwise doesn't even build it.
+    pub fn log(&self, level: Level, message: impl FnOnce() -> String) {
+        if self.logs(level) {
+            self.emit(|| Event::Log {
+                level,
+                message: message(),
+            });
+        }
+    }
+
+    pub fn explain(&self, message: impl FnOnce() -> String) {
+        self.log(Level::Explain, message);
+    }
+
+    pub fn info(&self, message: impl FnOnce() -> String) {
+        self.log(Level::Info, message);
+    }
+
+    pub fn debug(&self, message: impl FnOnce() -> String) {
+        self.log(Level::Debug, message);
+    }
+
+    /// Current values of all parameters, sorted by name.
+    #[must_use]
+    pub fn params(&self) -> Vec<(&'static str, &str)> {
+        let mut params: Vec<_> = self
+            .params
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        params.sort_unstable();
+        params
+    }
+
+    /// Whether events passed to [`Context::emit`] go anywhere.
+    #[must_use]
+    pub fn is_observed(&self) -> bool {
+        self.observer.is_some()
+    }
+
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn Observer>>) {
+        self.observer = observer;
+    }
+
+    /// Sends the event built by `event` to the observer. Without an observer the event isn't even
+    /// built, so emitting costs nothing in normal runs.
+    pub fn emit(&self, event: impl FnOnce() -> Event) {
+        if let Some(observer) = &self.observer {
+            observer.observe(event());
+        }
+    }
+
+    /// Overrides a declared parameter.
+    ///
+    /// # Errors
+    /// If the solution didn't declare a parameter called `name` or its check rejects `value`.
+    pub fn set_param(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        let Some(declared) = self.declared.iter().find(|param| param.name == name) else {
+            bail!("Unknown parameter {name}");
+        };
+        if let Err(reason) = (declared.check)(value) {
+            bail!("Invalid value {value:?} for parameter {name}: {reason}");
+        }
+        self.params.insert(declared.name, value.to_string());
+        Ok(())
+    }
+
+    /// Gets the current value of a declared parameter.
+    ///
+    /// # Panics
+    /// If the solution didn't declare a parameter called `name` or its value isn't a valid `T`.
+    #[must_use]
+    pub fn param<T: FromStr>(&self, name: &str) -> T {
+        let value = self
+            .params
+            .get(name)
+            .unwrap_or_else(|| panic!("Parameter {name} is not declared"));
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid value {value:?} for parameter {name}"))
+    }
+}
+
 pub trait Solution {
-    fn part1(&self, _input: &str) -> Option<SolvedValue> {
+    fn part1(&self, _input: &str, _ctx: &Context) -> Option<SolvedValue> {
         None
     }
-    fn part2(&self, _input: &str) -> Option<SolvedValue> {
+    fn part2(&self, _input: &str, _ctx: &Context) -> Option<SolvedValue> {
         None
     }
 
-    fn run(&self, input: &str, part: Part) -> Option<SolvedValue> {
+    /// Parameters this day can be tuned with via `--param` or a `.params` file.
+    fn parameters(&self) -> &'static [Parameter] {
+        &[]
+    }
+
+    /// Renders the input (and possibly the answers) for the `visualize` subcommand.
+    fn visualize(&self, _input: &str, _ctx: &Context) -> Option<Visualization> {
+        None
+    }
+
+    /// Alternatives to `part1` and `part2`, selectable with `--impl` and compared by `cross-check`.
+    fn implementations(&self) -> &'static [Implementation] {
+        &[]
+    }
+
+    /// Solves `part` with the implementation called `name`, or the default one if there is none.
+    fn run_named(&self, input: &str, ctx: &Context, part: Part, name: &str) -> Option<SolvedValue> {
+        match self
+            .implementations()
+            .iter()
+            .find(|implementation| implementation.part == part && implementation.name == name)
+        {
+            Some(implementation) => (implementation.solve)(input, ctx),
+            None => self.run(input, ctx, part),
+        }
+    }
+
+    /// Lists the assumptions of this day's solutions that `input` breaks, for `--validate`.
+    fn validate(&self, _input: &str, _ctx: &Context) -> Vec<Violation> {
+        Vec::new()
+    }
+
+    /// Generates a valid input with about `size` items for stress tests, see [`crate::generate`].
+    fn generate(&self, _size: usize, _rng: &mut Rng) -> Option<String> {
+        None
+    }
+
+    fn run(&self, input: &str, ctx: &Context, part: Part) -> Option<SolvedValue> {
         match part {
-            Part::One => self.part1(input),
-            Part::Two => self.part2(input),
+            Part::One => self.part1(input, ctx),
+            Part::Two => self.part2(input, ctx),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Part {
     One,
     Two,
@@ -64,6 +322,22 @@ impl Display for Part {
     }
 }
 
+/// Answers of all implementations of `part` for `input`, the default one first.
+pub fn answers(
+    solution: &dyn Solution,
+    input: &str,
+    ctx: &Context,
+    part: Part,
+) -> Vec<(&'static str, Option<SolvedValue>)> {
+    let mut answers = vec![(DEFAULT_IMPLEMENTATION, solution.run(input, ctx, part))];
+    for implementation in solution.implementations() {
+        if implementation.part == part {
+            answers.push((implementation.name, (implementation.solve)(input, ctx)));
+        }
+    }
+    answers
+}
+
 impl From<Part> for u8 {
     fn from(part: Part) -> u8 {
         match part {
@@ -72,3 +346,23 @@ impl From<Part> for u8 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_param() {
+        let mut ctx = Context::new(crate::days::get_day(8).unwrap().as_ref(), false);
+        assert_eq!(ctx.param::<usize>("connections"), 1000);
+        ctx.set_param("connections", "10").unwrap();
+        assert_eq!(ctx.param::<usize>("connections"), 10);
+        let error = ctx.set_param("connections", "abc").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid value \"abc\" for parameter connections: invalid digit found in string"
+        );
+        assert_eq!(ctx.param::<usize>("connections"), 10);
+        assert!(ctx.set_param("unknown", "1").is_err());
+    }
+}
@@ -1,23 +1,79 @@
 use std::fs::read_to_string;
 use std::path::Path;
 
+use anyhow::bail;
+
+use crate::solution::{Context, Solution};
+
 #[must_use]
 pub fn read_input(day: usize, example: bool, part: u8) -> Option<String> {
-    read_to_string(
-        get_possible_paths(day, example, part)
-            .iter()
-            .find(|path| Path::new(path).exists())?,
-    )
-    .ok()
-    .map(|s| s.replace('\r', ""))
+    read_first_existing(&get_possible_paths(day, example, part, "txt"))
+}
+
+/// Reads `key = value` lines from the `.params` file that belongs to the selected input.
+///
+/// Empty lines and lines starting with `#` are ignored.
+///
+/// # Errors
+/// If a line isn't `key = value`, naming the file and line.
+pub fn read_params(day: usize, example: bool, part: u8) -> anyhow::Result<Vec<(String, String)>> {
+    let Some(path) = params_path(day, example, part) else {
+        return Ok(Vec::new());
+    };
+    let content = read_to_string(&path)?.replace('\r', "");
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| match line.split_once('=') {
+            Some((key, value)) => Ok((key.trim().to_string(), value.trim().to_string())),
+            None => bail!(
+                "{path}: Expected `name = value` in line {}, found {line:?}",
+                i + 1
+            ),
+        })
+        .collect()
+}
+
+/// Builds the context for a part with defaults of `solution` overridden by the input's `.params` file.
+///
+/// # Errors
+/// If the `.params` file is malformed or sets a parameter the solution doesn't declare.
+pub fn read_context(
+    solution: &dyn Solution,
+    day: usize,
+    example: bool,
+    part: u8,
+) -> anyhow::Result<Context> {
+    let mut ctx = Context::new(solution, example);
+    for (key, value) in read_params(day, example, part)? {
+        if let Err(e) = ctx.set_param(&key, &value) {
+            let path = params_path(day, example, part).unwrap_or_default();
+            bail!("{path}: {e}");
+        }
+    }
+    Ok(ctx)
+}
+
+fn params_path(day: usize, example: bool, part: u8) -> Option<String> {
+    get_possible_paths(day, example, part, "params")
+        .into_iter()
+        .find(|path| Path::new(path).exists())
+}
+
+fn read_first_existing(paths: &[String]) -> Option<String> {
+    read_to_string(paths.iter().find(|path| Path::new(path).exists())?)
+        .ok()
+        .map(|s| s.replace('\r', ""))
 }
 
-fn get_possible_paths(day: usize, example: bool, part: u8) -> Vec<String> {
+fn get_possible_paths(day: usize, example: bool, part: u8, extension: &str) -> Vec<String> {
     let mut paths = Vec::new();
     if example {
-        paths.push(format!("inputs/{day:02}-example-{part}.txt"));
-        paths.push(format!("inputs/{day:02}-example.txt"));
+        paths.push(format!("inputs/{day:02}-example-{part}.{extension}"));
+        paths.push(format!("inputs/{day:02}-example.{extension}"));
     }
-    paths.push(format!("inputs/{day:02}.txt"));
+    paths.push(format!("inputs/{day:02}.{extension}"));
     paths
 }
@@ -0,0 +1,238 @@
+use std::fmt::{Display, Write};
+
+/// A rendering of a day's input, produced by the `visualize` subcommand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Visualization {
+    /// Graphviz source, turn it into an image with e.g. `dot -Tsvg`.
+    Dot(String),
+    /// An SVG image.
+    Svg(String),
+}
+
+impl Visualization {
+    /// File extension the content is usually stored with.
+    #[must_use]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Visualization::Dot(_) => "dot",
+            Visualization::Svg(_) => "svg",
+        }
+    }
+}
+
+impl Display for Visualization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Visualization::Dot(content) | Visualization::Svg(content) => write!(f, "{content}"),
+        }
+    }
+}
+
+/// Quotes `value` as a DOT string, so ids and labels can contain any character.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn attributes(attributes: &[(&str, &str)]) -> String {
+    if attributes.is_empty() {
+        return String::new();
+    }
+    let list: Vec<String> = attributes
+        .iter()
+        .map(|(key, value)| format!("{key}={}", quote(value)))
+        .collect();
+    format!(" [{}]", list.join(", "))
+}
+
+/// Builds a directed graph in the DOT language one statement at a time.
+#[derive(Debug, Clone)]
+pub struct DotGraph {
+    source: String,
+}
+
+impl DotGraph {
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            source: format!("digraph {} {{\n", quote(name)),
+        }
+    }
+
+    /// Sets attributes for the whole graph, e.g. `rankdir`.
+    #[must_use]
+    pub fn graph_attributes(mut self, list: &[(&str, &str)]) -> Self {
+        for (key, value) in list {
+            let _ = writeln!(self.source, "  {key}={};", quote(value));
+        }
+        self
+    }
+
+    /// Sets attributes every node starts with.
+    #[must_use]
+    pub fn node_defaults(mut self, list: &[(&str, &str)]) -> Self {
+        let _ = writeln!(self.source, "  node{};", attributes(list));
+        self
+    }
+
+    pub fn node(&mut self, id: &str, list: &[(&str, &str)]) {
+        let _ = writeln!(self.source, "  {}{};", quote(id), attributes(list));
+    }
+
+    pub fn edge(&mut self, from: &str, to: &str, list: &[(&str, &str)]) {
+        let _ = writeln!(
+            self.source,
+            "  {} -> {}{};",
+            quote(from),
+            quote(to),
+            attributes(list)
+        );
+    }
+
+    #[must_use]
+    pub fn finish(mut self) -> Visualization {
+        self.source.push_str("}\n");
+        Visualization::Dot(self.source)
+    }
+}
+
+/// Formats `value - offset`, which may be negative.
+fn shifted(value: usize, offset: usize) -> String {
+    if value >= offset {
+        (value - offset).to_string()
+    } else {
+        format!("-{}", offset - value)
+    }
+}
+
+/// Draws shapes in the coordinates of a puzzle into an SVG image of a fixed width.
+///
+/// Puzzle coordinates are used as they are and mapped to the image by the `viewBox`, so huge inputs
+/// don't need any rescaling. Strokes keep their width in pixels however far the image is scaled.
+#[derive(Debug, Clone)]
+pub struct SvgCanvas {
+    source: String,
+}
+
+impl SvgCanvas {
+    /// Creates a canvas `width` pixels wide showing everything from `min` to `max` with a small margin.
+    #[must_use]
+    pub fn new(min: (usize, usize), max: (usize, usize), width: usize) -> Self {
+        let (extent_x, extent_y) = (max.0 - min.0, max.1 - min.1);
+        let margin = extent_x.max(extent_y) / 20 + 1;
+        let (view_width, view_height) = (extent_x + 2 * margin, extent_y + 2 * margin);
+        let height = (width * view_height / view_width).max(1);
+        Self {
+            source: format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"{} {} {view_width} {view_height}\">\n",
+                shifted(min.0, margin),
+                shifted(min.1, margin),
+            ),
+        }
+    }
+
+    fn element(&mut self, name: &str, list: &[(&str, &str)]) {
+        let _ = write!(self.source, "  <{name}");
+        for (key, value) in list {
+            let value = value.replace('&', "&amp;").replace('"', "&quot;");
+            let _ = write!(self.source, " {key}=\"{value}\"");
+        }
+        let _ = writeln!(self.source, " vector-effect=\"non-scaling-stroke\"/>");
+    }
+
+    pub fn line(&mut self, from: (usize, usize), to: (usize, usize), list: &[(&str, &str)]) {
+        let coordinates = [
+            from.0.to_string(),
+            from.1.to_string(),
+            to.0.to_string(),
+            to.1.to_string(),
+        ];
+        let mut all = vec![
+            ("x1", coordinates[0].as_str()),
+            ("y1", coordinates[1].as_str()),
+            ("x2", coordinates[2].as_str()),
+            ("y2", coordinates[3].as_str()),
+        ];
+        all.extend_from_slice(list);
+        self.element("line", &all);
+    }
+
+    /// Draws the rectangle spanned by two opposite corners.
+    pub fn rect(&mut self, a: (usize, usize), b: (usize, usize), list: &[(&str, &str)]) {
+        let coordinates = [
+            a.0.min(b.0).to_string(),
+            a.1.min(b.1).to_string(),
+            a.0.abs_diff(b.0).to_string(),
+            a.1.abs_diff(b.1).to_string(),
+        ];
+        let mut all = vec![
+            ("x", coordinates[0].as_str()),
+            ("y", coordinates[1].as_str()),
+            ("width", coordinates[2].as_str()),
+            ("height", coordinates[3].as_str()),
+        ];
+        all.extend_from_slice(list);
+        self.element("rect", &all);
+    }
+
+    pub fn polygon(
+        &mut self,
+        points: impl IntoIterator<Item = (usize, usize)>,
+        list: &[(&str, &str)],
+    ) {
+        let points: Vec<String> = points
+            .into_iter()
+            .map(|(x, y)| format!("{x},{y}"))
+            .collect();
+        let points = points.join(" ");
+        let mut all = vec![("points", points.as_str())];
+        all.extend_from_slice(list);
+        self.element("polygon", &all);
+    }
+
+    #[must_use]
+    pub fn finish(mut self) -> Visualization {
+        self.source.push_str("</svg>\n");
+        Visualization::Svg(self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_graph() {
+        let mut graph = DotGraph::new("test")
+            .graph_attributes(&[("rankdir", "LR")])
+            .node_defaults(&[("shape", "box")]);
+        graph.node("a", &[("label", "a\n\"1\"")]);
+        graph.edge("a", "b", &[]);
+        graph.edge("b", "c", &[("color", "grey")]);
+        assert_eq!(
+            graph.finish().to_string(),
+            "digraph \"test\" {\n  rankdir=\"LR\";\n  node [shape=\"box\"];\n  \"a\" [label=\"a\\n\\\"1\\\"\"];\n  \"a\" -> \"b\";\n  \"b\" -> \"c\" [color=\"grey\"];\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_svg_canvas() {
+        let mut canvas = SvgCanvas::new((0, 0), (40, 20), 100);
+        canvas.rect((30, 5), (10, 15), &[("fill", "red")]);
+        canvas.polygon([(0, 0), (40, 0), (40, 20)], &[]);
+        assert_eq!(
+            canvas.finish().to_string(),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"56\" viewBox=\"-3 -3 46 26\">\n  <rect x=\"10\" y=\"5\" width=\"20\" height=\"10\" fill=\"red\" vector-effect=\"non-scaling-stroke\"/>\n  <polygon points=\"0,0 40,0 40,20\" vector-effect=\"non-scaling-stroke\"/>\n</svg>\n"
+        );
+    }
+}